@@ -0,0 +1,1201 @@
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use opencv::core::{Scalar, Size};
+use serde::{Deserialize, Serialize};
+use structopt::StructOpt;
+use crate::detectors::motion_mog2_params::MotionMog2Params;
+use crate::error::{Result, VisionError};
+use crate::sinks::video_output::fourcc;
+use crate::util::http::Url;
+use crate::util::video_frames::Tier;
+
+// environment overrides are named <ENV_PREFIX>_<SECTION>_<KEY>, e.g. RUSTY_VISION_MOG2_HISTORY
+const ENV_PREFIX: &str = "RUSTY_VISION";
+
+/// Command line arguments. Every tuning flag is optional, anything left unset falls back to the
+/// environment, the config file and finally the built-in defaults (see [`Settings::load`]).
+#[derive(StructOpt, Debug, Default)]
+#[structopt(name = "RustyVision", about = "OpenCV-based motion detection")]
+pub struct VideoConfig {
+
+    #[structopt(
+        long,
+        parse(from_os_str),
+        required_unless_one = &["device", "stream", "images"],
+        conflicts_with_all = &["device", "stream"]
+    )]
+    pub input: Option<PathBuf>,
+
+    /// Camera index (e.g. 0) or V4L2 device path (e.g. /dev/video0)
+    #[structopt(long, conflicts_with = "stream")]
+    pub device: Option<String>,
+
+    /// Network stream URL (e.g. rtsp://camera/stream or http://host/video.mjpg)
+    #[structopt(long)]
+    pub stream: Option<String>,
+
+    /// Directory of JPEG/PNG frames or printf-style pattern (e.g. frames/frame_%06d.png)
+    #[structopt(long, conflicts_with_all = &["input", "device", "stream"])]
+    pub images: Option<String>,
+
+    /// TOML or YAML settings file, overridden by RUSTY_VISION_<SECTION>_<KEY> variables and flags
+    #[structopt(long, parse(from_os_str))]
+    pub config: Option<PathBuf>,
+
+    /// Video file to write the annotated frames to, e.g. out.mp4 (the extension picks the container)
+    #[structopt(long)]
+    pub output: Option<String>,
+
+    /// FourCC of the codec used for --output, e.g. mp4v, MJPG or avc1 [default: mp4v]
+    #[structopt(long)]
+    pub output_codec: Option<String>,
+
+    /// Write the frames to --output without the motion overlay
+    #[structopt(long)]
+    pub output_raw: bool,
+
+    /// Directory to record motion triggered clips to, at full resolution
+    #[structopt(long)]
+    pub clips: Option<String>,
+
+    /// Recent video kept in memory and prepended to each clip [default: 3000]
+    #[structopt(long)]
+    pub pre_roll_ms: Option<u64>,
+
+    /// How long a clip carries on after the last motion [default: 5000]
+    #[structopt(long)]
+    pub post_roll_ms: Option<u64>,
+
+    /// JSON Lines file to log motion events to, or - for stdout (which implies --silent)
+    #[structopt(long)]
+    pub events: Option<String>,
+
+    /// CSV file to write the detector values and stage timings of every processed frame to
+    #[structopt(long)]
+    pub metrics: Option<String>,
+
+    /// ONNX model that classifies the regions of confirmed motion, on the CPU
+    #[structopt(long)]
+    pub classifier_model: Option<String>,
+
+    /// Class names of --classifier-model, one per line in output order
+    #[structopt(long)]
+    pub classifier_labels: Option<String>,
+
+    /// OpenAI or Ollama compatible endpoint that describes the regions of confirmed motion
+    #[structopt(long)]
+    pub vlm_url: Option<String>,
+
+    /// Model the --vlm-url endpoint is asked to use
+    #[structopt(long)]
+    pub vlm_model: Option<String>,
+
+    /// Frame rate at which an image sequence is played back [default: 25]
+    #[structopt(long)]
+    pub images_fps: Option<f64>,
+
+    /// Delay before the first reconnect attempt, doubled on each further attempt [default: 500]
+    #[structopt(long)]
+    pub reconnect_delay_ms: Option<u64>,
+
+    /// Upper bound for the delay between reconnect attempts [default: 30000]
+    #[structopt(long)]
+    pub reconnect_max_delay_ms: Option<u64>,
+
+    /// Reconnect attempts before giving up on a stream, 0 retries forever [default: 0]
+    #[structopt(long)]
+    pub reconnect_attempts: Option<u32>,
+
+    /// Time without a decoded frame after which a stream is considered stalled [default: 5000]
+    #[structopt(long)]
+    pub stall_timeout_ms: Option<u64>,
+
+    /// Consecutive failed reads tolerated from a live device before giving up [default: 30]
+    #[structopt(long)]
+    pub max_dropped_frames: Option<i32>,
+
+    #[structopt(long)]
+    pub silent: bool,
+
+    #[structopt(long)]
+    pub verbose: bool,
+
+    #[structopt(long)]
+    pub headless: bool,
+
+    /// [default: 2]
+    #[structopt(long)]
+    pub target_fps: Option<f64>,
+
+    /// [default: 0.4]
+    #[structopt(long)]
+    pub mse_threshold: Option<f64>,
+
+    /// [default: 5000]
+    #[structopt(long)]
+    pub mog2_threshold: Option<f64>,
+
+    /// MOG2 area below which a motion event ends, negative uses --mog2-threshold [default: -1]
+    #[structopt(long)]
+    pub motion_stop_threshold: Option<f64>,
+
+    /// Time motion has to last before an event starts [default: 0]
+    #[structopt(long)]
+    pub min_on_ms: Option<u64>,
+
+    /// Time without motion before an event ends [default: 0]
+    #[structopt(long)]
+    pub min_off_ms: Option<u64>,
+
+    /// Time after an event ends during which no new event starts [default: 0]
+    #[structopt(long)]
+    pub cooldown_ms: Option<u64>,
+
+    /// Background subtractor of the cascade, mog2 or knn; replaces the other in every stage
+    #[structopt(long, possible_values = &["mog2", "knn"])]
+    pub subtractor: Option<DetectorKind>,
+
+    /// [default: 5000]
+    #[structopt(long)]
+    pub knn_threshold: Option<f64>,
+
+    /// [default: 500]
+    #[structopt(long)]
+    pub knn_history: Option<i32>,
+
+    /// KNN squared distance threshold [default: 400]
+    #[structopt(long)]
+    pub knn_sensitivity: Option<f64>,
+
+    /// [default: 500]
+    #[structopt(long)]
+    pub mog2_history: Option<i32>,
+
+    /// Leave the shadows MOG2 detects out of the motion area
+    #[structopt(long)]
+    pub mog2_shadows: bool,
+
+    /// Count shadows as motion, even where the config detects them
+    #[structopt(long, conflicts_with = "mog2-shadows")]
+    pub no_mog2_shadows: bool,
+
+    /// [default: 50]
+    #[structopt(long)]
+    pub mog2_sensitivity: Option<f64>,
+
+    /// [default: 17]
+    #[structopt(long)]
+    pub adaptive_block_size: Option<i32>,
+
+    /// [default: 9]
+    #[structopt(long)]
+    pub adaptive_c: Option<f64>,
+}
+
+/// Console and window behaviour.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct OutputSettings {
+    pub silent: bool,
+    pub verbose: bool,
+    pub headless: bool,
+}
+
+/// Processing rate and the resolutions of the `VideoFrames` ladder.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FrameSettings {
+    pub target_fps: f64,
+    pub half_resolution: [i32; 2],      // [width, height]
+    pub quarter_resolution: [i32; 2],   // detectors run at this resolution
+    pub blur_kernel: [i32; 2],          // gaussian blur applied to the monochrome samples
+}
+
+impl Default for FrameSettings {
+    fn default() -> Self {
+        Self {
+            target_fps: 2.0,
+            half_resolution: [1280, 720],
+            quarter_resolution: [640, 360],
+            blur_kernel: [5, 5],
+        }
+    }
+}
+
+/// Playback and recovery of the frame sources.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SourceSettings {
+    pub images_fps: f64,
+    pub max_dropped_frames: i32,
+    pub reconnect_delay_ms: u64,
+    pub reconnect_max_delay_ms: u64,
+    pub reconnect_attempts: u32,        // 0 => retry forever
+    pub stall_timeout_ms: u64,
+}
+
+impl Default for SourceSettings {
+    fn default() -> Self {
+        Self {
+            images_fps: 25.0,
+            max_dropped_frames: 30,
+            reconnect_delay_ms: 500,
+            reconnect_max_delay_ms: 30000,
+            reconnect_attempts: 0,
+            stall_timeout_ms: 5000,
+        }
+    }
+}
+
+/// The mean squared error gate.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MseSettings {
+    pub threshold: f64,
+    pub window: usize,                  // frames averaged over
+}
+
+impl Default for MseSettings {
+    fn default() -> Self {
+        Self {
+            threshold: 0.4,
+            window: 10,
+        }
+    }
+}
+
+/// The KNN background subtractor, an alternative to MOG2. Its foreground mask goes through the
+/// same post-processing, configured in `[mog2]`, so that only the subtractor differs.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct KnnSettings {
+    pub threshold: f64,                 // average motion area that counts as motion
+    pub history: i32,
+    pub dist2_threshold: f64,           // squared distance to the samples up to which a pixel is background
+    pub learning_rate: f64,             // negative => chosen by OpenCV from the history
+}
+
+impl Default for KnnSettings {
+    fn default() -> Self {
+        Self {
+            threshold: 5000.0,
+            history: 500,
+            dist2_threshold: 400.0,
+            learning_rate: -1.0,
+        }
+    }
+}
+
+/// A detector that can be a stage of the cascade. Each takes its parameters from the section of
+/// the same name. The classifier is not one: it labels the boxes of confirmed motion once the
+/// cascade has run, and never gates it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DetectorKind {
+    Mse,
+    Mog2,
+    Knn,
+}
+
+impl DetectorKind {
+    // the background subtractors, which segment motion and can stand in for each other
+    pub fn is_subtractor(self) -> bool {
+        matches!(self, DetectorKind::Mog2 | DetectorKind::Knn)
+    }
+}
+
+impl FromStr for DetectorKind {
+    type Err = String;
+
+    fn from_str(name: &str) -> std::result::Result<Self, Self::Err> {
+        match name {
+            "mse" => Ok(DetectorKind::Mse),
+            "mog2" => Ok(DetectorKind::Mog2),
+            "knn" => Ok(DetectorKind::Knn),
+            _ => Err(format!("unknown detector '{}'", name)),
+        }
+    }
+}
+
+/// A stage of the detector cascade. Stages run in order, and one scoring below its threshold
+/// skips those after it; the threshold of the last stage is where motion events start.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CascadeStageSettings {
+    pub detector: DetectorKind,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub threshold: Option<f64>,         // None => the threshold in the detector's section
+    #[serde(default)]
+    pub tier: Tier,                     // the resolution the detector runs at
+}
+
+impl CascadeStageSettings {
+    pub fn new(detector: DetectorKind) -> Self {
+        Self {
+            detector,
+            threshold: None,
+            tier: Tier::Quarter,
+        }
+    }
+}
+
+/// Debouncing of the per frame motion into events. Motion starts at the threshold of the last
+/// cascade stage; the durations in frames and milliseconds both have to be met.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MotionEventSettings {
+    pub stop_threshold: f64,            // score of the last stage below which motion stops, negative => its threshold
+    pub min_on_frames: u32,             // processed frames of motion before an event starts
+    pub min_on_ms: u64,
+    pub min_off_frames: u32,            // processed frames without motion before an event ends
+    pub min_off_ms: u64,
+    pub cooldown_frames: u32,           // processed frames after an event before the next can start
+    pub cooldown_ms: u64,
+}
+
+impl Default for MotionEventSettings {
+    fn default() -> Self {
+        Self {
+            stop_threshold: -1.0,
+            min_on_frames: 1,
+            min_on_ms: 0,
+            min_off_frames: 1,
+            min_off_ms: 0,
+            cooldown_frames: 0,
+            cooldown_ms: 0,
+        }
+    }
+}
+
+/// Association and lifetime of the tracks that follow motion blobs across frames. Distances are
+/// in pixels at `frames.quarter_resolution`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TrackerSettings {
+    pub iou_threshold: f64,             // least overlap with a predicted box to match it
+    pub max_distance: f64,              // greatest distance between centres to match without overlap
+    pub min_hits: u32,                  // matches before a track is confirmed
+    pub max_missed: u32,                // updates a confirmed track survives without a match
+    pub history: usize,                 // centres kept per track
+    pub process_noise: f64,             // Kalman filter variances
+    pub measurement_noise: f64,
+}
+
+impl Default for TrackerSettings {
+    fn default() -> Self {
+        Self {
+            iou_threshold: 0.1,
+            max_distance: 40.0,
+            min_hits: 3,
+            max_missed: 5,
+            history: 50,
+            process_noise: 1.0,
+            measurement_noise: 10.0,
+        }
+    }
+}
+
+/// A line that tracks are counted across, in full resolution pixels. Side A is on the left when
+/// looking from `start` towards `end`.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TripwireSettings {
+    pub name: String,
+    pub start: [f64; 2],                // [x, y]
+    pub end: [f64; 2],
+}
+
+/// Whether a zone is an area of interest or an area to ignore.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ZoneKind {
+    #[default]
+    Include,
+    Exclude,
+}
+
+/// A polygon in full resolution pixels. Detections are restricted to the include zones (the
+/// whole frame when there are none) minus the exclude zones. Every zone also measures the
+/// motion inside it, in pixels at `frames.quarter_resolution`, raises its own events and
+/// reports tracks that stay inside it for `loiter_ms`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ZoneSettings {
+    pub name: String,
+    pub kind: ZoneKind,
+    pub points: Vec<[f64; 2]>,          // [x, y] corners, in drawing order
+    pub threshold: f64,                 // moving pixels inside the zone that count as motion
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stop_threshold: Option<f64>,    // moving pixels below which the zone's motion stops, None => threshold
+    pub loiter_ms: u64,                 // dwell time of a track that counts as loitering, 0 => never
+}
+
+impl Default for ZoneSettings {
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            kind: ZoneKind::Include,
+            points: Vec::new(),
+            threshold: 500.0,
+            stop_threshold: None,
+            loiter_ms: 0,
+        }
+    }
+}
+
+/// The annotated (or raw) video written alongside processing.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct VideoOutputSettings {
+    pub path: String,                   // empty => no video is written
+    pub codec: String,                  // FourCC
+    pub raw: bool,                      // leave out the motion overlay
+}
+
+impl Default for VideoOutputSettings {
+    fn default() -> Self {
+        Self {
+            path: String::new(),
+            codec: String::from("mp4v"),
+            raw: false,
+        }
+    }
+}
+
+/// Motion triggered clip recording.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ClipSettings {
+    pub directory: String,              // empty => no clips are recorded
+    pub pre_roll_ms: u64,
+    pub post_roll_ms: u64,
+    pub codec: String,                  // FourCC
+    pub container: String,              // file extension, e.g. mp4 or avi
+}
+
+impl Default for ClipSettings {
+    fn default() -> Self {
+        Self {
+            directory: String::new(),
+            pre_roll_ms: 3000,
+            post_roll_ms: 5000,
+            codec: String::from("mp4v"),
+            container: String::from("mp4"),
+        }
+    }
+}
+
+/// The JSON Lines motion event log.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct EventLogSettings {
+    pub path: String,                   // empty => no log, - => stdout
+}
+
+/// The per frame metrics CSV.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MetricsSettings {
+    pub path: String,                   // empty => no metrics are written
+}
+
+/// The optional image classifier run on the regions of confirmed motion. Crops are letterboxed
+/// to `input_size` and turned into a blob as `(pixel - mean) * scale`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ClassifierSettings {
+    pub model: String,                  // ONNX file, empty => no classification
+    pub labels: String,                 // class names, one per line; empty => class indices
+    pub input_size: [i32; 2],
+    pub scale: f64,
+    pub mean: [f64; 3],                 // in the channel order the model takes
+    pub swap_rb: bool,                  // feed the model RGB rather than BGR
+    pub softmax: bool,                  // the model outputs raw scores rather than probabilities
+    pub min_confidence: f64,            // less confident results are dropped
+    pub padding: f64,                   // added to each side of a box, as a fraction of its size
+}
+
+impl Default for ClassifierSettings {
+    fn default() -> Self {
+        Self {
+            model: String::new(),
+            labels: String::new(),
+            input_size: [224, 224],
+            scale: 1.0 / 255.0,
+            mean: [0.0, 0.0, 0.0],
+            swap_rb: true,
+            softmax: true,
+            min_confidence: 0.5,
+            padding: 0.1,
+        }
+    }
+}
+
+/// The request and response format of a vision-language model endpoint.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum VlmApi {
+    #[default]
+    #[serde(rename = "openai")]
+    OpenAi,                             // POST /v1/chat/completions, as served by llama.cpp, vLLM and LM Studio
+    #[serde(rename = "ollama")]
+    Ollama,                             // POST /api/chat
+}
+
+/// The optional vision-language model asked to describe the regions of confirmed motion. Its
+/// answers arrive asynchronously and are logged as events of their own.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct VlmSettings {
+    pub url: String,                    // http:// endpoint, empty => no descriptions
+    pub api: VlmApi,
+    pub model: String,
+    pub prompt: String,
+    pub api_key: String,                // sent as a bearer token unless empty
+    pub timeout_ms: u64,                // per request, including the connection
+    pub min_interval_ms: u64,           // between requests, a rate limit for the endpoint
+    pub max_in_flight: usize,           // requests waiting for an answer at once
+    pub max_crops: usize,               // the largest regions of a frame that are sent
+    pub max_crop_size: i32,             // longer crops are scaled down to this many pixels
+    pub jpeg_quality: i32,
+    pub padding: f64,                   // added to each side of a box, as a fraction of its size
+}
+
+impl Default for VlmSettings {
+    fn default() -> Self {
+        Self {
+            url: String::new(),
+            api: VlmApi::OpenAi,
+            model: String::new(),
+            prompt: String::from("Describe the moving objects in these images in one sentence."),
+            api_key: String::new(),
+            timeout_ms: 30000,
+            min_interval_ms: 5000,
+            max_in_flight: 1,
+            max_crops: 4,
+            max_crop_size: 512,
+            jpeg_quality: 85,
+            padding: 0.2,
+        }
+    }
+}
+
+/// Colours and line widths of the motion overlay. Colours are BGR.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct OverlaySettings {
+    pub tint_color: [f64; 3],
+    pub tint_alpha: f64,
+    pub show_shadows: bool,             // tints the shadows MOG2 left out of the motion, if it detects them
+    pub shadow_color: [f64; 3],
+    pub box_color: [f64; 3],
+    pub box_thickness: i32,
+    pub tripwire_color: [f64; 3],
+}
+
+impl Default for OverlaySettings {
+    fn default() -> Self {
+        Self {
+            tint_color: [0.0, 0.0, 255.0],
+            tint_alpha: 0.5,
+            show_shadows: true,
+            shadow_color: [255.0, 0.0, 0.0],
+            box_color: [0.0, 255.0, 0.0],
+            box_thickness: 2,
+            tripwire_color: [255.0, 255.0, 0.0],
+        }
+    }
+}
+
+/// Every tunable of the pipeline, resolved from defaults < config file < environment < command line.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Settings {
+    pub output: OutputSettings,
+    pub frames: FrameSettings,
+    pub source: SourceSettings,
+    pub mse: MseSettings,
+    pub mog2: MotionMog2Params,
+    pub knn: KnnSettings,
+    pub cascade: Vec<CascadeStageSettings>,
+    pub motion: MotionEventSettings,
+    pub tracker: TrackerSettings,
+    pub tripwires: Vec<TripwireSettings>,
+    pub zones: Vec<ZoneSettings>,
+    pub classifier: ClassifierSettings,
+    pub vlm: VlmSettings,
+    pub overlay: OverlaySettings,
+    pub video_output: VideoOutputSettings,
+    pub clips: ClipSettings,
+    pub events: EventLogSettings,
+    pub metrics: MetricsSettings,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            output: OutputSettings::default(),
+            frames: FrameSettings::default(),
+            source: SourceSettings::default(),
+            mse: MseSettings::default(),
+            mog2: MotionMog2Params::default(),
+            knn: KnnSettings::default(),
+            // the MSE gate in front of MOG2
+            cascade: vec![CascadeStageSettings::new(DetectorKind::Mse), CascadeStageSettings::new(DetectorKind::Mog2)],
+            motion: MotionEventSettings::default(),
+            tracker: TrackerSettings::default(),
+            tripwires: Vec::new(),
+            zones: Vec::new(),
+            classifier: ClassifierSettings::default(),
+            vlm: VlmSettings::default(),
+            overlay: OverlaySettings::default(),
+            video_output: VideoOutputSettings::default(),
+            clips: ClipSettings::default(),
+            events: EventLogSettings::default(),
+            metrics: MetricsSettings::default(),
+        }
+    }
+}
+
+impl Settings {
+    pub fn load(cli: &VideoConfig) -> Result<Self> {
+        let mut settings = match &cli.config {
+            Some(path) => Self::from_file(path)?,
+            None => Self::default(),
+        };
+        settings.apply_env(|name| env::var(name).ok())?;
+        settings.apply_cli(cli);
+
+        // the event log owns stdout when it is written there
+        if settings.events.path == "-" {
+            settings.output.silent = true;
+        }
+        settings.validate()?;
+        Ok(settings)
+    }
+
+    // the format is picked from the extension; keys missing from the file keep their defaults
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let contents = fs::read_to_string(path).map_err(|e| {
+            VisionError::InvalidConfig(format!("unable to read {}: {}", path.display(), e))
+        })?;
+        let format = path.extension()
+            .and_then(|extension| extension.to_str())
+            .unwrap_or_default()
+            .to_ascii_lowercase();
+        Self::parse(&contents, &format)
+            .map_err(|reason| VisionError::InvalidConfig(format!("{}: {}", path.display(), reason)))
+    }
+
+    fn parse(contents: &str, format: &str) -> std::result::Result<Self, String> {
+        match format {
+            "toml" => toml::from_str(contents).map_err(|e| e.to_string()),
+            "yaml" | "yml" => serde_yaml::from_str(contents).map_err(|e| e.to_string()),
+            other => Err(format!("unsupported format '{}', expected .toml, .yaml or .yml", other)),
+        }
+    }
+
+    // overrides every setting that has a matching environment variable
+    pub fn apply_env<F>(&mut self, lookup: F) -> Result<()>
+    where
+        F: Fn(&str) -> Option<String>,
+    {
+        let invalid = |e: &dyn std::fmt::Display| VisionError::InvalidConfig(e.to_string());
+
+        let mut value = toml::Value::try_from(&*self).map_err(|e| invalid(&e))?;
+        let mut overridden = false;
+        if let toml::Value::Table(sections) = &mut value {
+            for (section, fields) in sections.iter_mut() {
+                let toml::Value::Table(fields) = fields else {
+                    continue;
+                };
+                for (key, current) in fields.iter_mut() {
+                    let name = format!("{}_{}_{}", ENV_PREFIX, section, key).to_ascii_uppercase();
+                    if let Some(raw) = lookup(&name) {
+                        *current = parse_env_value(&name, &raw, current)?;
+                        overridden = true;
+                    }
+                }
+            }
+        }
+
+        if overridden {
+            *self = value.try_into().map_err(|e| invalid(&e))?;
+        }
+        Ok(())
+    }
+
+    pub fn apply_cli(&mut self, cli: &VideoConfig) {
+        if cli.silent {
+            self.output.silent = true;
+        }
+        if cli.verbose {
+            self.output.verbose = true;
+        }
+        if cli.headless {
+            self.output.headless = true;
+        }
+        if let Some(output) = &cli.output {
+            self.video_output.path = output.clone();
+        }
+        if let Some(output_codec) = &cli.output_codec {
+            self.video_output.codec = output_codec.clone();
+        }
+        if cli.output_raw {
+            self.video_output.raw = true;
+        }
+        if let Some(clips) = &cli.clips {
+            self.clips.directory = clips.clone();
+        }
+        if let Some(pre_roll_ms) = cli.pre_roll_ms {
+            self.clips.pre_roll_ms = pre_roll_ms;
+        }
+        if let Some(post_roll_ms) = cli.post_roll_ms {
+            self.clips.post_roll_ms = post_roll_ms;
+        }
+        if let Some(events) = &cli.events {
+            self.events.path = events.clone();
+        }
+        if let Some(metrics) = &cli.metrics {
+            self.metrics.path = metrics.clone();
+        }
+        if let Some(classifier_model) = &cli.classifier_model {
+            self.classifier.model = classifier_model.clone();
+        }
+        if let Some(classifier_labels) = &cli.classifier_labels {
+            self.classifier.labels = classifier_labels.clone();
+        }
+        if let Some(vlm_url) = &cli.vlm_url {
+            self.vlm.url = vlm_url.clone();
+        }
+        if let Some(vlm_model) = &cli.vlm_model {
+            self.vlm.model = vlm_model.clone();
+        }
+        if let Some(target_fps) = cli.target_fps {
+            self.frames.target_fps = target_fps;
+        }
+        if let Some(images_fps) = cli.images_fps {
+            self.source.images_fps = images_fps;
+        }
+        if let Some(max_dropped_frames) = cli.max_dropped_frames {
+            self.source.max_dropped_frames = max_dropped_frames;
+        }
+        if let Some(reconnect_delay_ms) = cli.reconnect_delay_ms {
+            self.source.reconnect_delay_ms = reconnect_delay_ms;
+        }
+        if let Some(reconnect_max_delay_ms) = cli.reconnect_max_delay_ms {
+            self.source.reconnect_max_delay_ms = reconnect_max_delay_ms;
+        }
+        if let Some(reconnect_attempts) = cli.reconnect_attempts {
+            self.source.reconnect_attempts = reconnect_attempts;
+        }
+        if let Some(stall_timeout_ms) = cli.stall_timeout_ms {
+            self.source.stall_timeout_ms = stall_timeout_ms;
+        }
+        if let Some(mse_threshold) = cli.mse_threshold {
+            self.mse.threshold = mse_threshold;
+        }
+        if let Some(mog2_threshold) = cli.mog2_threshold {
+            self.mog2.threshold = mog2_threshold;
+        }
+        if let Some(motion_stop_threshold) = cli.motion_stop_threshold {
+            self.motion.stop_threshold = motion_stop_threshold;
+        }
+        if let Some(min_on_ms) = cli.min_on_ms {
+            self.motion.min_on_ms = min_on_ms;
+        }
+        if let Some(min_off_ms) = cli.min_off_ms {
+            self.motion.min_off_ms = min_off_ms;
+        }
+        if let Some(cooldown_ms) = cli.cooldown_ms {
+            self.motion.cooldown_ms = cooldown_ms;
+        }
+        if let Some(subtractor) = cli.subtractor {
+            for stage in self.cascade.iter_mut().filter(|stage| stage.detector.is_subtractor()) {
+                stage.detector = subtractor;
+            }
+        }
+        if let Some(knn_threshold) = cli.knn_threshold {
+            self.knn.threshold = knn_threshold;
+        }
+        if let Some(knn_history) = cli.knn_history {
+            self.knn.history = knn_history;
+        }
+        if let Some(knn_sensitivity) = cli.knn_sensitivity {
+            self.knn.dist2_threshold = knn_sensitivity;
+        }
+        if let Some(mog2_history) = cli.mog2_history {
+            self.mog2.history = mog2_history;
+        }
+        if cli.mog2_shadows {
+            self.mog2.detect_shadows = true;
+        }
+        if cli.no_mog2_shadows {
+            self.mog2.detect_shadows = false;
+        }
+        if let Some(mog2_sensitivity) = cli.mog2_sensitivity {
+            self.mog2.var_threshold = mog2_sensitivity;
+        }
+        if let Some(adaptive_block_size) = cli.adaptive_block_size {
+            self.mog2.adaptive_block_size = adaptive_block_size;
+        }
+        if let Some(adaptive_c) = cli.adaptive_c {
+            self.mog2.adaptive_c = adaptive_c;
+        }
+    }
+
+    // the threshold a stage gates at, its own or else that of its detector's section
+    pub fn threshold(&self, stage: &CascadeStageSettings) -> f64 {
+        stage.threshold.unwrap_or(match stage.detector {
+            DetectorKind::Mse => self.mse.threshold,
+            DetectorKind::Mog2 => self.mog2.threshold,
+            DetectorKind::Knn => self.knn.threshold,
+        })
+    }
+
+    // reports every out of range setting at once rather than stopping at the first
+    pub fn validate(&self) -> Result<()> {
+        let mut problems = Vec::new();
+        let mut check = |valid: bool, problem: String| {
+            if !valid {
+                problems.push(problem);
+            }
+        };
+
+        let frames = &self.frames;
+        check(frames.target_fps > 0.0, format!("frames.target_fps must be positive, got {}", frames.target_fps));
+        check(is_positive(frames.half_resolution), format!("frames.half_resolution must be positive, got {:?}", frames.half_resolution));
+        check(is_positive(frames.quarter_resolution), format!("frames.quarter_resolution must be positive, got {:?}", frames.quarter_resolution));
+        check(
+            frames.quarter_resolution[0] <= frames.half_resolution[0] && frames.quarter_resolution[1] <= frames.half_resolution[1],
+            format!("frames.quarter_resolution {:?} must not exceed frames.half_resolution {:?}", frames.quarter_resolution, frames.half_resolution),
+        );
+        check(is_odd(frames.blur_kernel), format!("frames.blur_kernel must be odd and positive, got {:?}", frames.blur_kernel));
+
+        let source = &self.source;
+        check(source.images_fps > 0.0, format!("source.images_fps must be positive, got {}", source.images_fps));
+        check(source.max_dropped_frames >= 0, format!("source.max_dropped_frames must not be negative, got {}", source.max_dropped_frames));
+        check(
+            source.reconnect_max_delay_ms >= source.reconnect_delay_ms,
+            format!("source.reconnect_max_delay_ms ({}) must not be below source.reconnect_delay_ms ({})", source.reconnect_max_delay_ms, source.reconnect_delay_ms),
+        );
+
+        let mse = &self.mse;
+        check(mse.threshold >= 0.0, format!("mse.threshold must not be negative, got {}", mse.threshold));
+        check(mse.window > 0, String::from("mse.window must be at least 1"));
+
+        let knn = &self.knn;
+        check(knn.threshold >= 0.0, format!("knn.threshold must not be negative, got {}", knn.threshold));
+        check(knn.history > 0, format!("knn.history must be positive, got {}", knn.history));
+        check(knn.dist2_threshold > 0.0, format!("knn.dist2_threshold must be positive, got {}", knn.dist2_threshold));
+        check(knn.learning_rate <= 1.0, format!("knn.learning_rate must not exceed 1, got {}", knn.learning_rate));
+
+        check(!self.cascade.is_empty(), String::from("cascade needs at least one stage"));
+        for (index, stage) in self.cascade.iter().enumerate() {
+            check(
+                !self.cascade[..index].iter().any(|other| other.detector == stage.detector),
+                format!("cascade[{}].detector {:?} is used more than once", index, stage.detector),
+            );
+            check(
+                stage.threshold.is_none_or(|threshold| threshold >= 0.0),
+                format!("cascade[{}].threshold must not be negative, got {:?}", index, stage.threshold),
+            );
+        }
+
+        let motion = &self.motion;
+        if let Some(last) = self.cascade.last() {
+            check(
+                motion.stop_threshold <= self.threshold(last),
+                format!("motion.stop_threshold ({}) must not exceed the threshold of the last cascade stage ({})", motion.stop_threshold, self.threshold(last)),
+            );
+        }
+        check(motion.min_on_frames > 0, String::from("motion.min_on_frames must be at least 1"));
+        check(motion.min_off_frames > 0, String::from("motion.min_off_frames must be at least 1"));
+
+        let tracker = &self.tracker;
+        check(
+            tracker.iou_threshold > 0.0 && tracker.iou_threshold <= 1.0,
+            format!("tracker.iou_threshold must be above 0 and at most 1, got {}", tracker.iou_threshold),
+        );
+        check(tracker.max_distance >= 0.0, format!("tracker.max_distance must not be negative, got {}", tracker.max_distance));
+        check(tracker.min_hits > 0, String::from("tracker.min_hits must be at least 1"));
+        check(tracker.history > 0, String::from("tracker.history must be at least 1"));
+        check(
+            tracker.process_noise > 0.0 && tracker.measurement_noise > 0.0,
+            format!("tracker.process_noise and tracker.measurement_noise must be positive, got {} and {}", tracker.process_noise, tracker.measurement_noise),
+        );
+
+        for (index, tripwire) in self.tripwires.iter().enumerate() {
+            check(!tripwire.name.is_empty(), format!("tripwires[{}].name must not be empty", index));
+            check(
+                !self.tripwires[..index].iter().any(|other| other.name == tripwire.name),
+                format!("tripwires[{}].name '{}' is used more than once", index, tripwire.name),
+            );
+            check(tripwire.start != tripwire.end, format!("tripwires[{}] must have distinct start and end points", index));
+        }
+
+        for (index, zone) in self.zones.iter().enumerate() {
+            check(!zone.name.is_empty(), format!("zones[{}].name must not be empty", index));
+            check(
+                !self.zones[..index].iter().any(|other| other.name == zone.name),
+                format!("zones[{}].name '{}' is used more than once", index, zone.name),
+            );
+            check(zone.points.len() >= 3, format!("zones[{}] needs at least 3 points, got {}", index, zone.points.len()));
+            check(zone.threshold > 0.0, format!("zones[{}].threshold must be positive, got {}", index, zone.threshold));
+            check(
+                zone.stop_threshold.is_none_or(|stop| (0.0..=zone.threshold).contains(&stop)),
+                format!("zones[{}].stop_threshold must be between 0 and its threshold ({}), got {:?}", index, zone.threshold, zone.stop_threshold),
+            );
+        }
+
+        let classifier = &self.classifier;
+        check(is_positive(classifier.input_size), format!("classifier.input_size must be positive, got {:?}", classifier.input_size));
+        check(classifier.scale > 0.0, format!("classifier.scale must be positive, got {}", classifier.scale));
+        check(
+            (0.0..=1.0).contains(&classifier.min_confidence),
+            format!("classifier.min_confidence must be between 0 and 1, got {}", classifier.min_confidence),
+        );
+        check(classifier.padding >= 0.0, format!("classifier.padding must not be negative, got {}", classifier.padding));
+
+        let vlm = &self.vlm;
+        check(
+            vlm.url.is_empty() || Url::parse(&vlm.url).is_ok(),
+            format!("vlm.url must be an http://host[:port]/path URL, got '{}'", vlm.url),
+        );
+        check(vlm.timeout_ms > 0, String::from("vlm.timeout_ms must be positive"));
+        check(vlm.max_in_flight > 0, String::from("vlm.max_in_flight must be positive"));
+        check(vlm.max_crops > 0, String::from("vlm.max_crops must be positive"));
+        check(vlm.max_crop_size > 0, format!("vlm.max_crop_size must be positive, got {}", vlm.max_crop_size));
+        check(
+            (1..=100).contains(&vlm.jpeg_quality),
+            format!("vlm.jpeg_quality must be between 1 and 100, got {}", vlm.jpeg_quality),
+        );
+        check(vlm.padding >= 0.0, format!("vlm.padding must not be negative, got {}", vlm.padding));
+
+        let overlay = &self.overlay;
+        check(is_color(overlay.tint_color), format!("overlay.tint_color must be within 0-255, got {:?}", overlay.tint_color));
+        check(is_color(overlay.shadow_color), format!("overlay.shadow_color must be within 0-255, got {:?}", overlay.shadow_color));
+        check(is_color(overlay.box_color), format!("overlay.box_color must be within 0-255, got {:?}", overlay.box_color));
+        check(is_color(overlay.tripwire_color), format!("overlay.tripwire_color must be within 0-255, got {:?}", overlay.tripwire_color));
+        check(
+            (0.0..=1.0).contains(&overlay.tint_alpha),
+            format!("overlay.tint_alpha must be between 0 and 1, got {}", overlay.tint_alpha),
+        );
+        check(overlay.box_thickness > 0, format!("overlay.box_thickness must be positive, got {}", overlay.box_thickness));
+
+        let video_output = &self.video_output;
+        check(
+            video_output.path.is_empty() || fourcc(&video_output.codec).is_ok(),
+            format!("video_output.codec must be a four character code, got '{}'", video_output.codec),
+        );
+
+        let clips = &self.clips;
+        check(
+            clips.directory.is_empty() || fourcc(&clips.codec).is_ok(),
+            format!("clips.codec must be a four character code, got '{}'", clips.codec),
+        );
+        check(
+            !clips.container.is_empty() && clips.container.chars().all(|c| c.is_ascii_alphanumeric()),
+            format!("clips.container must be a file extension such as mp4, got '{}'", clips.container),
+        );
+
+        problems.extend(self.mog2.problems("mog2."));
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(VisionError::InvalidConfig(problems.join("; ")))
+        }
+    }
+}
+
+pub fn size(dimensions: [i32; 2]) -> Size {
+    Size::new(dimensions[0], dimensions[1])
+}
+
+pub fn color(bgr: [f64; 3]) -> Scalar {
+    Scalar::new(bgr[0], bgr[1], bgr[2], 0.0)
+}
+
+fn is_positive(dimensions: [i32; 2]) -> bool {
+    dimensions.iter().all(|d| *d > 0)
+}
+
+fn is_odd(dimensions: [i32; 2]) -> bool {
+    dimensions.iter().all(|d| *d > 0 && d % 2 == 1)
+}
+
+fn is_color(bgr: [f64; 3]) -> bool {
+    bgr.iter().all(|channel| (0.0..=255.0).contains(channel))
+}
+
+// parses an environment override as a TOML literal of the same type as the value it replaces
+fn parse_env_value(name: &str, raw: &str, current: &toml::Value) -> Result<toml::Value> {
+    let parsed = match current {
+        toml::Value::String(_) => Some(toml::Value::String(raw.to_string())),
+        _ => format!("value = {}", raw)
+            .parse::<toml::Table>()
+            .ok()
+            .and_then(|mut table| table.remove("value")),
+    };
+
+    match (current, parsed) {
+        (toml::Value::Float(_), Some(toml::Value::Integer(integer))) => Ok(toml::Value::Float(integer as f64)),
+        (_, Some(value)) if value.same_type(current) => Ok(value),
+        _ => Err(VisionError::InvalidConfig(format!(
+            "{} must be {}, got '{}'",
+            name,
+            current.type_str(),
+            raw
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn lookup(vars: &[(&str, &str)]) -> impl Fn(&str) -> Option<String> {
+        let vars: HashMap<String, String> = vars.iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect();
+        move |name| vars.get(name).cloned()
+    }
+
+    #[test]
+    fn defaults_are_valid() {
+        assert!(Settings::default().validate().is_ok());
+    }
+
+    #[test]
+    fn example_file_lists_the_defaults() {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("config.example.toml");
+        assert_eq!(Settings::from_file(&path).unwrap(), Settings::default());
+    }
+
+    #[test]
+    fn file_overrides_only_the_keys_it_sets() {
+        let toml = "[mog2]\nhistory = 200\nerode_kernel = [3, 3]\n\n[overlay]\ntint_alpha = 0.25\n";
+        let yaml = "mog2:\n  history: 200\n  erode_kernel: [3, 3]\noverlay:\n  tint_alpha: 0.25\n";
+
+        let settings = Settings::parse(toml, "toml").unwrap();
+        assert_eq!(settings.mog2.history, 200);
+        assert_eq!(settings.mog2.erode_kernel, [3, 3]);
+        assert_eq!(settings.mog2.var_threshold, MotionMog2Params::default().var_threshold);
+        assert_eq!(settings.overlay.tint_alpha, 0.25);
+        assert_eq!(settings.frames, FrameSettings::default());
+        assert_eq!(Settings::parse(yaml, "yaml").unwrap(), settings);
+    }
+
+    #[test]
+    fn unknown_keys_are_rejected() {
+        let error = Settings::parse("[mog2]\nhistroy = 200\n", "toml").unwrap_err();
+        assert!(error.contains("histroy"), "{}", error);
+        assert!(Settings::parse("", "ini").is_err());
+    }
+
+    #[test]
+    fn environment_overrides_file_and_cli_overrides_environment() {
+        let mut settings = Settings::parse("[mog2]\nhistory = 200\nvar_threshold = 30.0\n", "toml").unwrap();
+        settings.apply_env(lookup(&[
+            ("RUSTY_VISION_MOG2_HISTORY", "300"),
+            ("RUSTY_VISION_MOG2_VAR_THRESHOLD", "40"),
+            ("RUSTY_VISION_FRAMES_BLUR_KERNEL", "[3, 3]"),
+        ])).unwrap();
+        assert_eq!(settings.mog2.history, 300);
+        assert_eq!(settings.mog2.var_threshold, 40.0);
+        assert_eq!(settings.frames.blur_kernel, [3, 3]);
+
+        settings.apply_cli(&VideoConfig {
+            mog2_history: Some(400),
+            ..VideoConfig::default()
+        });
+        assert_eq!(settings.mog2.history, 400);
+        assert_eq!(settings.mog2.var_threshold, 40.0);
+    }
+
+    #[test]
+    fn environment_values_of_the_wrong_type_are_rejected() {
+        let mut settings = Settings::default();
+        let error = settings.apply_env(lookup(&[("RUSTY_VISION_MOG2_HISTORY", "lots")])).unwrap_err();
+        assert!(error.to_string().contains("RUSTY_VISION_MOG2_HISTORY"), "{}", error);
+        assert_eq!(settings, Settings::default());
+    }
+
+    #[test]
+    fn validation_reports_every_problem() {
+        let mut settings = Settings::default();
+        settings.mog2.adaptive_block_size = 16;
+        settings.frames.blur_kernel = [4, 5];
+        settings.overlay.tint_alpha = 2.0;
+
+        let error = settings.validate().unwrap_err().to_string();
+        assert!(error.contains("mog2.adaptive_block_size"), "{}", error);
+        assert!(error.contains("frames.blur_kernel"), "{}", error);
+        assert!(error.contains("overlay.tint_alpha"), "{}", error);
+    }
+
+    #[test]
+    fn zones_are_read_as_polygons() {
+        let toml = "[[zones]]\nname = \"driveway\"\npoints = [[0.0, 400.0], [640.0, 300.0], [640.0, 720.0]]\n\n\
+                    [[zones]]\nname = \"tree\"\nkind = \"exclude\"\npoints = [[900.0, 0.0], [1280.0, 0.0]]\n";
+        let mut settings = Settings::parse(toml, "toml").unwrap();
+        assert_eq!(settings.zones[0].kind, ZoneKind::Include);
+        assert_eq!(settings.zones[0].points[1], [640.0, 300.0]);
+        assert_eq!(settings.zones[1].kind, ZoneKind::Exclude);
+        assert_eq!(settings.zones[1].threshold, ZoneSettings::default().threshold);
+
+        settings.zones[1].threshold = 0.0;
+        settings.zones[1].stop_threshold = Some(1.0);
+        let error = settings.validate().unwrap_err().to_string();
+        assert!(error.contains("zones[1].stop_threshold must be between 0 and its threshold"), "{}", error);
+        assert!(error.contains("zones[1] needs at least 3 points"), "{}", error);
+        assert!(error.contains("zones[1].threshold must be positive"), "{}", error);
+        assert!(!error.contains("zones[0]"), "{}", error);
+    }
+
+    #[test]
+    fn cascade_stages_fall_back_to_their_detector_thresholds() {
+        let toml = "[mog2]\nthreshold = 8000.0\n\n\
+                    [[cascade]]\ndetector = \"mog2\"\ntier = \"half\"\n\n\
+                    [[cascade]]\ndetector = \"mse\"\nthreshold = 2.5\n";
+        let settings = Settings::parse(toml, "toml").unwrap();
+        assert_eq!(settings.cascade.len(), 2);
+        assert_eq!(settings.cascade[0].tier, Tier::Half);
+        assert_eq!(settings.threshold(&settings.cascade[0]), 8000.0);
+        assert_eq!(settings.cascade[1].tier, Tier::Quarter);
+        assert_eq!(settings.threshold(&settings.cascade[1]), 2.5);
+        assert!(settings.validate().is_ok());
+
+        let mut settings = Settings::default();
+        settings.cascade.push(CascadeStageSettings::new(DetectorKind::Mse));
+        let error = settings.validate().unwrap_err().to_string();
+        assert!(error.contains("cascade[2].detector Mse is used more than once"), "{}", error);
+    }
+
+    #[test]
+    fn subtractor_flag_swaps_mog2_for_knn() {
+        let mut settings = Settings::parse("[knn]\nthreshold = 3000.0\n", "toml").unwrap();
+        settings.apply_cli(&VideoConfig {
+            subtractor: Some("knn".parse().unwrap()),
+            ..VideoConfig::default()
+        });
+        let detectors: Vec<DetectorKind> = settings.cascade.iter().map(|stage| stage.detector).collect();
+        assert_eq!(detectors, vec![DetectorKind::Mse, DetectorKind::Knn]);
+        assert_eq!(settings.threshold(&settings.cascade[1]), 3000.0);
+        assert!("mog3".parse::<DetectorKind>().is_err());
+    }
+
+    #[test]
+    fn shadow_detection_can_be_turned_off_on_the_command_line() {
+        let mut settings = Settings::parse("[mog2]\ndetect_shadows = true\n", "toml").unwrap();
+        settings.apply_cli(&VideoConfig {
+            no_mog2_shadows: true,
+            ..VideoConfig::default()
+        });
+        assert!(!settings.mog2.detect_shadows);
+        assert!(VideoConfig::from_iter_safe(["rusty_vision", "--input", "a.mp4", "--no-mog2-shadows"]).is_ok());
+        assert!(VideoConfig::from_iter_safe(["rusty_vision", "--input", "a.mp4", "--mog2-shadows", "--no-mog2-shadows"]).is_err());
+    }
+}
@@ -0,0 +1,230 @@
+use opencv::core::{Mat, Rect};
+use opencv::imgproc;
+use crate::config::{DetectorKind, Settings};
+use crate::detectors::detector::Detector;
+use crate::detectors::mean_squared_error::MeanSquaredError;
+use crate::detectors::motion_knn::MotionKnn;
+use crate::detectors::motion_mog2::MotionMog2;
+use crate::error::Result;
+use crate::masks::zones::ZoneMask;
+use crate::util::stop_watch::StopWatch;
+use crate::util::video_frames::{Tier, VideoFrames};
+
+/// The motion found by the deepest stage that segments it, at quarter resolution whatever tier
+/// the stage ran at.
+pub struct Segmentation {
+    pub mask: Mat,                      // motion that counts, as the overlay tints it
+    pub foreground: Mat,                // motion before the detection mask
+    pub boxes: Vec<Rect>,
+    pub shadows: Option<Mat>,           // cast shadows, left out of the mask, if the stage looks for them
+    pub shadow_fraction: Option<f64>,
+}
+
+/// A detector of the cascade, the threshold it gates at and what it made of the latest frame.
+pub struct Stage {
+    detector: Box<dyn Detector>,
+    kind: DetectorKind,
+    threshold: f64,
+    value: Option<f64>,                 // None when an earlier stage skipped this one
+    score: Option<f64>,
+}
+
+impl Stage {
+    pub fn name(&self) -> &str {
+        self.detector.name()
+    }
+
+    pub fn tier(&self) -> Tier {
+        self.detector.tier()
+    }
+
+    // the metrics column of the stage's value, e.g. `mse` or `mog2_area` for a subtractor
+    pub fn column(&self) -> String {
+        let name = self.name().to_lowercase();
+        if self.kind.is_subtractor() { format!("{}_area", name) } else { name }
+    }
+
+    pub fn threshold(&self) -> f64 {
+        self.threshold
+    }
+
+    pub fn value(&self) -> Option<f64> {
+        self.value
+    }
+
+    pub fn score(&self) -> Option<f64> {
+        self.score
+    }
+
+    // whether the stage ran and reached its threshold
+    pub fn passed(&self) -> bool {
+        self.score.is_some_and(|score| score >= self.threshold)
+    }
+}
+
+/// The detectors of `Settings::cascade`, run in order. A stage scoring below its threshold
+/// skips the stages after it. The last stage decides whether there is motion, and the deepest
+/// stage that ran and found boxes supplies them, with its masks, to tracking, zones and the
+/// overlay.
+pub struct Cascade {
+    stages: Vec<Stage>,
+    segmentation: Option<Segmentation>,
+}
+
+impl Cascade {
+    // the detectors start from the current samples of their tiers
+    pub fn new(settings: &Settings, video_frames: &VideoFrames) -> Result<Self> {
+        let mut stages = Vec::with_capacity(settings.cascade.len());
+        for stage in &settings.cascade {
+            let frame = &video_frames.mono.tier(stage.tier).cur;
+            let detector: Box<dyn Detector> = match stage.detector {
+                DetectorKind::Mse => Box::new(MeanSquaredError::with_window(frame, settings.mse.window)?.with_tier(stage.tier)),
+                DetectorKind::Mog2 => Box::new(MotionMog2::from_params(frame, settings.mog2.clone())?.with_tier(stage.tier)),
+                DetectorKind::Knn => Box::new(MotionKnn::new(frame, settings.knn.clone(), &settings.mog2)?.with_tier(stage.tier)),
+            };
+            stages.push(Stage {
+                detector,
+                kind: stage.detector,
+                threshold: settings.threshold(stage),
+                value: None,
+                score: None,
+            });
+        }
+        Ok(Self {
+            stages,
+            segmentation: None,
+        })
+    }
+
+    pub fn stages(&self) -> &[Stage] {
+        &self.stages
+    }
+
+    // the threshold of the last stage, where motion starts
+    pub fn threshold(&self) -> f64 {
+        self.stages.last().map(Stage::threshold).unwrap_or_default()
+    }
+
+    // the score of the last stage, None when an earlier stage held it back
+    pub fn score(&self) -> Option<f64> {
+        self.stages.last().and_then(Stage::score)
+    }
+
+    pub fn is_motion(&self) -> bool {
+        self.stages.last().is_some_and(Stage::passed)
+    }
+
+    pub fn segmentation(&self) -> Option<&Segmentation> {
+        self.segmentation.as_ref()
+    }
+
+    // restricts every stage to the detection mask of the zones, taken at the stage's tier
+    pub fn set_mask(&mut self, mask: Option<&ZoneMask>) -> Result<()> {
+        for stage in &mut self.stages {
+            let tier = stage.tier();
+            stage.detector.set_mask(mask.map(|mask| mask.tier(tier).try_clone()).transpose()?);
+        }
+        Ok(())
+    }
+
+    // each stage that runs is timed as a lap named after its detector
+    pub fn update(&mut self, video_frames: &VideoFrames, stopwatch: &mut StopWatch) -> Result<()> {
+        self.segmentation = None;
+        let mut gated = false;
+        for stage in &mut self.stages {
+            if gated {
+                stage.value = None;
+                stage.score = None;
+                continue;
+            }
+
+            let detection = stage.detector.detect(video_frames)?;
+            stage.value = Some(detection.value);
+            stage.score = Some(detection.score);
+            if let (Some(mask), Some(foreground), Some(boxes)) = (detection.mask, detection.foreground, detection.boxes) {
+                self.segmentation = Some(Segmentation {
+                    mask: to_quarter(mask, detection.tier, video_frames)?,
+                    foreground: to_quarter(foreground, detection.tier, video_frames)?,
+                    boxes: boxes.iter()
+                        .map(|rect| video_frames.rescale(*rect, detection.tier, Tier::Quarter))
+                        .collect(),
+                    shadows: detection.shadows
+                        .map(|shadows| to_quarter(shadows, detection.tier, video_frames))
+                        .transpose()?,
+                    shadow_fraction: detection.shadow_fraction,
+                });
+            }
+            stopwatch.lap(stage.detector.name());
+            gated = !stage.passed();
+        }
+        Ok(())
+    }
+}
+
+// nearest neighbour keeps a binary mask binary
+fn to_quarter(mask: &Mat, tier: Tier, video_frames: &VideoFrames) -> Result<Mat> {
+    if tier == Tier::Quarter {
+        return Ok(mask.try_clone()?);
+    }
+    let mut quarter = Mat::default();
+    imgproc::resize(mask, &mut quarter, video_frames.size_quarter(), 0.0, 0.0, imgproc::INTER_NEAREST)?;
+    Ok(quarter)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use opencv::core::{MatTraitConst, Size};
+    use crate::config::CascadeStageSettings;
+    use crate::sources::synthetic_scene::{Shape, SyntheticScene};
+
+    fn run(settings: &Settings) -> (Cascade, StopWatch) {
+        let mut scene = SyntheticScene::fixture(Some(Shape::Rectangle));
+        let mut video_frames = VideoFrames::new(Size::new(1280, 720), Size::new(640, 360), Size::new(320, 180)).unwrap();
+        assert!(video_frames.read_frame(&mut scene).unwrap());
+        let mut cascade = Cascade::new(settings, &video_frames).unwrap();
+        let mut stopwatch = StopWatch::new();
+        while video_frames.read_frame(&mut scene).unwrap() {
+            stopwatch.start();
+            cascade.update(&video_frames, &mut stopwatch).unwrap();
+            stopwatch.tick();
+        }
+        (cascade, stopwatch)
+    }
+
+    #[test]
+    fn a_stage_below_its_threshold_skips_the_rest() {
+        let mut settings = Settings::default();
+        settings.cascade[0].threshold = Some(1000.0);
+        let (cascade, stopwatch) = run(&settings);
+
+        let [mse, mog2] = cascade.stages() else { panic!("expected two stages") };
+        assert!(mse.score().is_some() && !mse.passed());
+        assert_eq!(mog2.score(), None);
+        assert_eq!(cascade.score(), None);
+        assert!(!cascade.is_motion());
+        assert!(cascade.segmentation().is_none());
+        let laps: Vec<&str> = stopwatch.frame_laps().iter().map(|(_, label)| label.as_str()).collect();
+        assert_eq!(laps, vec!["MSE"]);
+    }
+
+    #[test]
+    fn segments_at_the_tier_of_the_stage() {
+        let mut settings = Settings::default();
+        settings.cascade = vec![CascadeStageSettings {
+            threshold: Some(0.0),
+            tier: Tier::Half,
+            ..CascadeStageSettings::new(DetectorKind::Mog2)
+        }];
+        let (cascade, _) = run(&settings);
+
+        assert!(cascade.is_motion());
+        let segmentation = cascade.segmentation().expect("MOG2 segments motion");
+        assert_eq!(segmentation.mask.size().unwrap(), Size::new(320, 180));
+        assert_eq!(segmentation.foreground.size().unwrap(), Size::new(320, 180));
+
+        // the object is 160 pixels wide at full resolution, i.e. 40 at quarter resolution
+        let widest = segmentation.boxes.iter().map(|rect| rect.width).max().expect("no motion boxed");
+        assert!((30..=50).contains(&widest), "{:?}", segmentation.boxes);
+    }
+}
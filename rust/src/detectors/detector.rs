@@ -0,0 +1,73 @@
+use opencv::core::{Mat, Rect};
+use crate::error::Result;
+use crate::util::video_frames::{Tier, VideoFrames};
+
+/// What a detector made of the latest frame, borrowed from the detector until its next update.
+#[derive(Debug, Clone, Copy)]
+pub struct Detection<'a> {
+    pub value: f64,                     // the signal of the latest frame alone
+    pub score: f64,                     // what a gate compares against its threshold
+    pub mask: Option<&'a Mat>,          // where the detector saw motion that counts
+    pub foreground: Option<&'a Mat>,    // the same before the detection mask, for measuring excluded areas
+    pub boxes: Option<&'a [Rect]>,      // in pixels of the tier
+    pub shadows: Option<&'a Mat>,       // cast shadows, left out of the mask, if the detector looks for them
+    pub shadow_fraction: Option<f64>,   // the share of the changed pixels that were shadows
+    pub tier: Tier,                     // the resolution the masks and boxes are at
+}
+
+/// A detector stage that can stand in for any other: it is updated with the latest frames and
+/// answers with a score, and a mask and boxes if it finds those.
+pub trait Detector {
+    fn name(&self) -> &str;
+
+    fn tier(&self) -> Tier;
+
+    fn detect(&mut self, video_frames: &VideoFrames) -> Result<Detection<'_>>;
+
+    // a binary mask at the detector's tier, outside of which motion is ignored; detectors that
+    // do not segment motion have no use for it
+    fn set_mask(&mut self, _mask: Option<Mat>) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use opencv::core::{Point, Scalar, Size};
+    use crate::detectors::mean_squared_error::MeanSquaredError;
+    use crate::detectors::motion_mog2::MotionMog2;
+    use crate::sources::synthetic_scene::{SyntheticObject, SyntheticScene};
+
+    #[test]
+    fn detectors_are_interchangeable() {
+        let mut scene = SyntheticScene::new(Size::new(1280, 720), 25.0, 40, 5)
+            .unwrap()
+            .with_object(SyntheticObject::rectangle(
+                Point::new(100, 280),
+                Size::new(160, 160),
+                (16.0, 0.0),
+                Scalar::all(255.0),
+            ));
+        let mut video_frames = VideoFrames::new(Size::new(1280, 720), Size::new(640, 360), Size::new(320, 180)).unwrap();
+        assert!(video_frames.read_frame(&mut scene).unwrap());
+
+        let quarter = &video_frames.mono.quarter.cur;
+        let mut detectors: Vec<Box<dyn Detector>> = vec![
+            Box::new(MeanSquaredError::new(quarter).unwrap()),
+            Box::new(MotionMog2::new(quarter, 500, 50.0, 17, 9.0).unwrap()),
+        ];
+        let mut scores = vec![0.0; detectors.len()];
+        while video_frames.read_frame(&mut scene).unwrap() {
+            for (detector, score) in detectors.iter_mut().zip(scores.iter_mut()) {
+                let detection = detector.detect(&video_frames).unwrap();
+                assert_eq!(detection.tier, Tier::Quarter);
+                assert!(detection.mask.is_some());
+                *score = detection.score;
+            }
+        }
+
+        assert_eq!(detectors[0].name(), "MSE");
+        assert_eq!(detectors[1].name(), "MOG2");
+        assert!(scores.iter().all(|score| *score > 0.0), "{:?}", scores);
+        assert!(detectors[0].detect(&video_frames).unwrap().boxes.is_none());
+    }
+}
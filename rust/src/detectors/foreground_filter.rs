@@ -0,0 +1,427 @@
+use opencv::core::{
+    self,
+    Mat,
+    MatExpr,
+    MatExprTraitConst,
+    MatTraitConst,
+    Point,
+    Vec4i,
+    Vector,
+    Scalar,
+};
+use opencv::imgproc::{self, adaptive_threshold, erode, morphology_ex, dilate, find_contours, draw_contours, bounding_rect};
+use opencv::Error;
+use opencv::video::BackgroundSubtractorTrait;
+use crate::config::size;
+use crate::detectors::detector::Detection;
+use crate::detectors::motion_mog2_params::MotionMog2Params;
+use crate::error;
+use crate::util::video_frames::Tier;
+
+/// The post-processing shared by the background subtractors: adaptive thresholding, morphology
+/// and contour extraction turn a raw foreground mask into a motion mask, bounding boxes and the
+/// total motion area, averaged over a window. Pixels the subtractor marks as shadows are taken
+/// out beforehand and kept in a mask of their own.
+pub struct ForegroundFilter {
+
+    diff_mask: Mat,                 // motion mask (source for frame copy operations)
+    dst_frame: Mat,                 // destination for frame copy operations
+
+    mask: Option<Mat>,              // where motion counts, e.g. the zones at quarter resolution
+    foreground: Mat,                // motion before the mask was applied
+    masked: Mat,
+
+    shadow_value: Option<f64>,      // what the subtractor marks shadows with, None when it does not
+    shadows: Mat,
+    shadow_fraction: f64,
+
+    default_scalar: Scalar,
+
+    adaptive_max_value: f64,
+    adaptive_method: i32,
+    adaptive_type: i32,
+    adaptive_block_size: i32,
+    adaptive_c: f64,
+
+    erode_iterations: i32,
+    erode_border_type: i32,
+    erode_kernel: MatExpr,
+    erode_anchor_point: Point,
+
+    dilate_iterations: i32,
+    dilate_border_type: i32,
+    dilate_kernel: MatExpr,
+    dilate_anchor_point: Point,
+
+    close_operation: i32,
+    close_iterations: i32,
+    close_border_type: i32,
+    close_kernel: MatExpr,
+    close_anchor_point: Point,
+
+    contour_mode: i32,
+    contour_method: i32,
+    contours: Vector<Vector<Point>>,
+    contour_anchor_point: Point,
+    contour_fill_anchor_point: Point,
+    contour_index: i32,
+    contour_thickness: i32,
+    contour_line_type: i32,
+    contour_max_level: i32,
+    contour_color: Scalar,
+
+    bounding_boxes: Vec<core::Rect>,
+    bounding_box_min_area: i32,
+
+    area_values: Vec<f64>,
+
+    _total_area: i32,
+    _contour_area: i32,
+    _contour_p1: Point,
+    _contour_p2: Point,
+}
+
+impl ForegroundFilter {
+    // only the post-processing parameters are used, the subtractor's own are left to its owner
+    pub fn new(prv_frame: &Mat, params: &MotionMog2Params) -> error::Result<Self> {
+        Ok(Self {
+            diff_mask: Mat::zeros(
+                prv_frame.rows(),
+                prv_frame.cols(),
+                prv_frame.typ(),
+            )?.to_mat()?,
+            dst_frame: Mat::zeros(
+                prv_frame.rows(),
+                prv_frame.cols(),
+                prv_frame.typ(),
+            )?.to_mat()?,
+
+            mask: None,
+            foreground: Mat::default(),
+            masked: Mat::default(),
+
+            shadow_value: None,
+            shadows: Mat::default(),
+            shadow_fraction: 0.0,
+
+            default_scalar: Scalar::default(),
+
+            adaptive_max_value: 255.0,
+            adaptive_method: imgproc::ADAPTIVE_THRESH_GAUSSIAN_C,
+            adaptive_type: imgproc::THRESH_BINARY_INV,
+            adaptive_block_size: params.adaptive_block_size,
+            adaptive_c: params.adaptive_c,
+
+            erode_kernel: Mat::ones_size(
+                size(params.erode_kernel),
+                core::CV_8U
+            )?,
+            erode_anchor_point: Point::new(-1, -1),
+            erode_iterations: params.erode_iterations,
+            erode_border_type: params.erode_border.to_cv(),
+
+            dilate_kernel: Mat::ones_size(
+                size(params.dilate_kernel),
+                core::CV_8U,
+            )?,
+            dilate_anchor_point: Point::new(-1, -1),
+            dilate_iterations: params.dilate_iterations,
+            dilate_border_type: params.dilate_border.to_cv(),
+
+            close_operation: imgproc::MORPH_CLOSE,
+            close_kernel: Mat::ones_size(
+                size(params.close_kernel),
+                core::CV_8U,
+            )?,
+            close_anchor_point: Point::new(-1, -1),
+            close_iterations: params.close_iterations,
+            close_border_type: params.close_border.to_cv(),
+
+            contour_mode: params.contour_mode.to_cv(),
+            contour_method: params.contour_approximation.to_cv(),
+            contours: Vector::new(),
+            contour_color: Scalar::new(
+                255.0,
+                255.0,
+                255.0,
+                0.0
+            ),
+            contour_anchor_point: Point::new(-1, -1),
+            contour_index: -1,
+            contour_thickness: imgproc::FILLED,
+            contour_line_type: imgproc::LINE_8,
+            contour_max_level: 0,
+            contour_fill_anchor_point: Point::new(-1, -1),
+
+            bounding_boxes: Vec::with_capacity(10),
+            bounding_box_min_area: params.min_box_area,
+
+            area_values: vec![0.0; params.window.max(1)],
+
+            _total_area: 0,
+            _contour_area: 0,
+            _contour_p1: Point::new(-1, -1),
+            _contour_p2: Point::new(-1, -1),
+        })
+    }
+
+    // for subtractors that detect shadows and mark them with `shadow_value`
+    pub fn with_shadows(mut self, shadow_value: i32) -> Self {
+        self.shadow_value = Some(shadow_value as f64);
+        self
+    }
+
+    // a binary mask at the subtractor's tier, outside of which motion is ignored
+    pub fn set_mask(&mut self, mask: Option<Mat>) {
+        self.mask = mask;
+    }
+
+    // the binary motion mask of the latest frame, before the mask was applied
+    pub fn get_foreground(&self) -> &Mat {
+        if self.mask.is_some() { &self.foreground } else { &self.dst_frame }
+    }
+
+    pub fn get_diff_mask(&self) -> &Mat {
+        &self.diff_mask
+    }
+
+    // the shadows of the latest frame, within the mask; None unless shadows are detected
+    pub fn get_shadows(&self) -> Option<&Mat> {
+        self.shadow_value.map(|_| &self.shadows)
+    }
+
+    // the share of the changed pixels that were shadows rather than motion
+    pub fn shadow_fraction(&self) -> Option<f64> {
+        self.shadow_value.map(|_| self.shadow_fraction)
+    }
+
+    pub fn get_contours(&self) -> &Vector<Vector<Point>> {
+        &self.contours
+    }
+
+    pub fn get_values(&self) -> &Vec<f64> {
+        &self.area_values
+    }
+
+    pub fn get_bounding_boxes(&self) -> &Vec<core::Rect> {
+        &self.bounding_boxes
+    }
+
+    // updates the subtractor with the frame and cleans up the foreground mask it returns
+    pub fn apply<S: BackgroundSubtractorTrait>(&mut self, subtractor: &mut S, cur_frame: &Mat, learning_rate: f64) -> Result<(), Error> {
+
+        self.contours.clear();
+
+        subtractor.apply(
+            cur_frame,
+            &mut self.diff_mask,
+            learning_rate
+        )?;
+
+        // shadows are taken out before the clean-up, so that they are neither boxed nor counted
+        if let Some(shadow_value) = self.shadow_value {
+            core::compare(&self.diff_mask, &Scalar::all(shadow_value), &mut self.shadows, core::CMP_EQ)?;
+            imgproc::threshold(&self.diff_mask, &mut self.dst_frame, shadow_value, 255.0, imgproc::THRESH_BINARY)?;
+            std::mem::swap(&mut self.diff_mask, &mut self.dst_frame);
+            if let Some(mask) = &self.mask {
+                core::bitwise_and(&self.shadows, mask, &mut self.masked, &core::no_array())?;
+                std::mem::swap(&mut self.shadows, &mut self.masked);
+            }
+
+            // measured on what the subtractor marked, before the clean-up grows or shrinks it
+            let shadow_pixels = core::count_non_zero(&self.shadows)? as f64;
+            let foreground_pixels = match &self.mask {
+                Some(mask) => {
+                    core::bitwise_and(&self.diff_mask, mask, &mut self.masked, &core::no_array())?;
+                    core::count_non_zero(&self.masked)?
+                }
+                None => core::count_non_zero(&self.diff_mask)?,
+            };
+            let changed_pixels = shadow_pixels + foreground_pixels as f64;
+            self.shadow_fraction = if changed_pixels > 0.0 { shadow_pixels / changed_pixels } else { 0.0 };
+        }
+
+        adaptive_threshold(
+            &self.diff_mask,
+            &mut self.dst_frame,
+            self.adaptive_max_value,
+            self.adaptive_method,
+            self.adaptive_type,
+            self.adaptive_block_size,
+            self.adaptive_c,
+        )?;
+
+        erode(
+            &self.dst_frame,
+            &mut self.diff_mask,
+            &self.erode_kernel,
+            self.erode_anchor_point,
+            self.erode_iterations,
+            self.erode_border_type,
+            self.default_scalar,
+        )?;
+
+        dilate(
+            &self.dst_frame,
+            &mut self.diff_mask,
+            &self.dilate_kernel,
+            self.dilate_anchor_point,
+            self.dilate_iterations,
+            self.dilate_border_type,
+            self.default_scalar,
+        )?;
+
+        morphology_ex(
+            &self.diff_mask,
+            &mut self.dst_frame,
+            self.close_operation,
+            &self.close_kernel,
+            self.close_anchor_point,
+            self.close_iterations,
+            self.close_border_type,
+            self.default_scalar,
+        )?;
+
+        // masked motion yields neither contours nor area
+        if let Some(mask) = &self.mask {
+            std::mem::swap(&mut self.dst_frame, &mut self.foreground);
+            core::bitwise_and(&self.foreground, mask, &mut self.dst_frame, &core::no_array())?;
+            core::bitwise_and(&self.diff_mask, mask, &mut self.masked, &core::no_array())?;
+            std::mem::swap(&mut self.diff_mask, &mut self.masked);
+        }
+
+        find_contours(
+            &self.dst_frame,
+            &mut self.contours,
+            self.contour_mode,
+            self.contour_method,
+            self.contour_anchor_point,
+        )?;
+
+        // filter bounding boxes
+        self.bounding_boxes = self.contours.iter()
+            .filter_map(|c| bounding_rect(&c).ok())
+            .filter(|rect| rect.area() >= self.bounding_box_min_area)
+            .collect();
+
+        // Sort rectangles by area (largest first) to optimize nesting check
+        self.bounding_boxes.sort_unstable_by_key(|rect| -(rect.area() as i64));
+
+        draw_contours(
+            &mut self.diff_mask,
+            &self.contours,
+            self.contour_index,
+            self.contour_color,
+            self.contour_thickness,
+            self.contour_line_type,
+            &Vector::<Vec4i>::new(),
+            self.contour_max_level,
+            self.contour_fill_anchor_point,
+        )?;
+
+        Ok(())
+    }
+
+    // adds the area of the latest contours to the window
+    pub fn calc_total_area(&mut self) -> Result<(), Error> {
+        self._total_area = 0;
+        self._contour_area = 0;
+        for index in 0..self.contours.len() {
+            if let Ok(contour) = self.contours.get(index) {
+                for i in 0..contour.len() {
+                    self._contour_p1 = contour.get(i)?;
+                    self._contour_p2 = contour.get((i + 1) % contour.len())?;
+                    self._contour_area += (self._contour_p1.x - self._contour_p2.x) * (self._contour_p1.y + self._contour_p2.y);
+                }
+                self. _total_area += self._contour_area.abs();
+            }
+        }
+        self.area_values.remove(0);
+        self.area_values.push(self._total_area as f64);
+        Ok(())
+    }
+
+    pub fn get_area(&self) -> f64 {
+        self.area_values.last().copied().unwrap_or_default()
+    }
+
+    pub fn area_avg(&self) -> f64 {
+        self.area_values.iter().sum::<f64>() / self.area_values.len() as f64
+    }
+
+    // scored by the average motion area, once `calc_total_area` has added the latest frame
+    pub fn detection(&self, tier: Tier) -> Detection<'_> {
+        Detection {
+            value: self.get_area(),
+            score: self.area_avg(),
+            mask: Some(&self.diff_mask),
+            foreground: Some(self.get_foreground()),
+            boxes: Some(&self.bounding_boxes),
+            shadows: self.get_shadows(),
+            shadow_fraction: self.shadow_fraction(),
+            tier,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use opencv::core::{Rect, Size};
+    use crate::config::KnnSettings;
+    use crate::detectors::detector::Detector;
+    use crate::detectors::motion_knn::MotionKnn;
+    use crate::detectors::motion_mog2::MotionMog2;
+    use crate::sources::synthetic_scene::{Shape, SyntheticScene};
+    use crate::util::video_frames::VideoFrames;
+
+    // the name, boxes and score each subtractor ends the scene with
+    fn detect(mut scene: SyntheticScene) -> Vec<(String, Vec<Rect>, f64)> {
+        let mut video_frames = VideoFrames::new(Size::new(1280, 720), Size::new(640, 360), Size::new(320, 180)).unwrap();
+        assert!(video_frames.read_frame(&mut scene).unwrap());
+
+        let quarter = &video_frames.mono.quarter.cur;
+        let params = MotionMog2Params::default();
+        let mut detectors: Vec<Box<dyn Detector>> = vec![
+            Box::new(MotionMog2::from_params(quarter, params.clone()).unwrap()),
+            Box::new(MotionKnn::new(quarter, KnnSettings::default(), &params).unwrap()),
+        ];
+        let mut results = vec![(Vec::new(), 0.0); detectors.len()];
+        while video_frames.read_frame(&mut scene).unwrap() {
+            for (detector, result) in detectors.iter_mut().zip(results.iter_mut()) {
+                let detection = detector.detect(&video_frames).unwrap();
+                *result = (detection.boxes.map(<[Rect]>::to_vec).unwrap_or_default(), detection.score);
+            }
+        }
+        detectors.iter()
+            .zip(results)
+            .map(|(detector, (boxes, score))| (detector.name().to_string(), boxes, score))
+            .collect()
+    }
+
+    #[test]
+    fn static_scene_has_no_motion() {
+        for (name, boxes, score) in detect(SyntheticScene::fixture(None)) {
+            assert!(boxes.is_empty(), "{} boxed {:?}", name, boxes);
+            assert_eq!(score, 0.0, "{}", name);
+        }
+    }
+
+    #[test]
+    fn moving_object_is_boxed() {
+        let scene = SyntheticScene::fixture(Some(Shape::Rectangle));
+        // where the object is on the last of the 101 frames
+        let expected = scene.objects()[0].bounds_at(100);
+        assert_eq!(expected, Rect::new(480, 280, 160, 160));
+
+        // quarter resolution boxes are a quarter the size of the synthetic frame's
+        for (name, boxes, score) in detect(scene) {
+            let found = boxes.iter().any(|rect| {
+                let scaled = Rect::new(rect.x * 4, rect.y * 4, rect.width * 4, rect.height * 4);
+                (scaled & expected).area() * 2 >= expected.area()
+            });
+            assert!(found, "{} matched no box to {:?} in {:?}", name, expected, boxes);
+            assert!(score > 0.0, "{}", name);
+        }
+    }
+}
@@ -5,49 +5,54 @@ use opencv::core::{
     MatTraitConst
 };
 use opencv::Error;
-use crate::util::video_frames::{FrameProcessor, VideoFrames};
+use crate::detectors::detector::{Detection, Detector};
+use crate::error::{self, VisionError};
+use crate::util::video_frames::{FrameProcessor, Tier, VideoFrames};
 
 const SAMPLE_COUNT: usize = 10;
 
+/// Mean absolute difference between the previous and current frames, at quarter resolution
+/// unless told otherwise, averaged over the last few frames. Cheap enough to run on every frame
+/// as a first gate.
 pub struct MeanSquaredError {
+    tier: Tier,
     diff_mask: Mat,
     sqr_diff: Mat,
 
-    num_pixels: f64,
     multiply_scale: f64,
     dtype: i32,
     mse_values: Vec<f64>,
 }
 
 impl<'a> MeanSquaredError {
-    pub fn new(prv_frame: &'a Mat) -> Self {
-        Self {
+    pub fn new(prv_frame: &'a Mat) -> error::Result<Self> {
+        Self::with_window(prv_frame, SAMPLE_COUNT)
+    }
+
+    // averages over the last `window` frames instead of the last SAMPLE_COUNT
+    pub fn with_window(prv_frame: &'a Mat, window: usize) -> error::Result<Self> {
+        Ok(Self {
+            tier: Tier::Quarter,
             diff_mask: Mat::zeros(
                 prv_frame.rows(),
                 prv_frame.cols(),
                 prv_frame.typ(),
-            ).unwrap().to_mat().unwrap(),
+            )?.to_mat()?,
             sqr_diff: Mat::zeros(
                 prv_frame.rows(),
                 prv_frame.cols(),
                 prv_frame.typ(),
-            ).unwrap().to_mat().unwrap(),
-            num_pixels: (prv_frame.rows() * prv_frame.cols()) as f64,
+            )?.to_mat()?,
             multiply_scale: 1.0,
             dtype: -1,
-            mse_values: vec![0.0; SAMPLE_COUNT],
-        }
+            mse_values: vec![0.0; window.max(1)],
+        })
     }
 
-    pub fn default() -> Self {
-        Self {
-            diff_mask: Mat::default(),
-            sqr_diff: Mat::default(),
-            num_pixels: 0.0,
-            multiply_scale: 1.0,
-            dtype: -1,
-            mse_values: Vec::default(),
-        }
+    // runs on the samples of another tier, which `prv_frame` should have been taken from
+    pub fn with_tier(mut self, tier: Tier) -> Self {
+        self.tier = tier;
+        self
     }
 
     pub fn get_diff_mask(&self) -> &Mat {
@@ -55,7 +60,7 @@ impl<'a> MeanSquaredError {
     }
 
     pub fn get_value(&self) -> f64 {
-        self.mse_values[SAMPLE_COUNT - 1]
+        self.mse_values.last().copied().unwrap_or_default()
     }
 
     pub fn get_value_avg(&self) -> f64 {
@@ -65,7 +70,7 @@ impl<'a> MeanSquaredError {
     fn _calculate_mse(&mut self, prv_frame: &'a Mat, cur_frame: &'a Mat) -> Result<(), Error> {
 
         core::absdiff(
-            &prv_frame,
+            prv_frame,
             cur_frame,
             &mut self.diff_mask
         )?;
@@ -80,14 +85,117 @@ impl<'a> MeanSquaredError {
         )?;
 
         self.mse_values.remove(0);
-        self.mse_values.push(core::sum_elems(&self.diff_mask)?[0] / self.num_pixels);
+        // taken from the frame, so that a default detector works at any tier
+        let num_pixels = (cur_frame.rows() * cur_frame.cols()).max(1) as f64;
+        self.mse_values.push(core::sum_elems(&self.diff_mask)?[0] / num_pixels);
 
         Ok(())
     }
 }
 
+impl Default for MeanSquaredError {
+    fn default() -> Self {
+        Self {
+            tier: Tier::Quarter,
+            diff_mask: Mat::default(),
+            sqr_diff: Mat::default(),
+            multiply_scale: 1.0,
+            dtype: -1,
+            mse_values: vec![0.0; SAMPLE_COUNT],
+        }
+    }
+}
+
 impl<'a> FrameProcessor<'a> for MeanSquaredError {
-    fn update(&mut self, video_frames: &VideoFrames) -> Result<(), Error> {
-        self._calculate_mse(&video_frames.mono.quarter.prev, &video_frames.mono.quarter.cur)
+    fn update(&mut self, video_frames: &VideoFrames) -> error::Result<()> {
+        let frame = video_frames.mono.tier(self.tier);
+        self._calculate_mse(&frame.prev, &frame.cur)
+            .map_err(VisionError::detector("MSE"))
     }
-}
\ No newline at end of file
+}
+
+impl Detector for MeanSquaredError {
+    fn name(&self) -> &str {
+        "MSE"
+    }
+
+    fn tier(&self) -> Tier {
+        self.tier
+    }
+
+    // scored by the average over the window, the difference of the latest frame is the mask
+    fn detect(&mut self, video_frames: &VideoFrames) -> error::Result<Detection<'_>> {
+        self.update(video_frames)?;
+        Ok(Detection {
+            value: self.get_value(),
+            score: self.get_value_avg(),
+            mask: Some(&self.diff_mask),
+            foreground: Some(&self.diff_mask),
+            boxes: None,
+            shadows: None,
+            shadow_fraction: None,
+            tier: self.tier,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use opencv::core::{Point, Scalar, Size};
+    use crate::sources::synthetic_scene::{SyntheticObject, SyntheticScene};
+
+    fn mse_avg(mut scene: SyntheticScene) -> f64 {
+        let mut video_frames = VideoFrames::new(
+            Size::new(1280, 720),
+            Size::new(640, 360),
+            Size::new(320, 180),
+        ).unwrap();
+        assert!(video_frames.read_frame(&mut scene).unwrap());
+        let mut mse = MeanSquaredError::new(&video_frames.mono.quarter.cur).unwrap();
+        while video_frames.read_frame(&mut scene).unwrap() {
+            mse.update(&video_frames).unwrap();
+        }
+        mse.get_value_avg()
+    }
+
+    fn scene() -> SyntheticScene {
+        SyntheticScene::new(Size::new(1280, 720), 25.0, 20, 3).unwrap()
+    }
+
+    #[test]
+    fn static_scene_has_no_error() {
+        assert_eq!(mse_avg(scene()), 0.0);
+    }
+
+    #[test]
+    fn moving_object_exceeds_default_threshold() {
+        let scene = scene().with_object(SyntheticObject::rectangle(
+            Point::new(100, 280),
+            Size::new(160, 160),
+            (16.0, 0.0),
+            Scalar::all(255.0),
+        ));
+        assert!(mse_avg(scene) > 0.4);
+    }
+
+    #[test]
+    fn lighting_ramp_shifts_every_pixel() {
+        let avg = mse_avg(scene().with_lighting_ramp(2.0));
+        assert!((avg - 2.0).abs() < 0.1, "unexpected MSE {}", avg);
+    }
+
+    #[test]
+    fn default_detector_measures_from_the_first_update() {
+        let mut scene = scene().with_lighting_ramp(2.0);
+        let mut video_frames = VideoFrames::new(Size::new(1280, 720), Size::new(640, 360), Size::new(320, 180)).unwrap();
+        assert!(video_frames.read_frame(&mut scene).unwrap());
+        assert!(video_frames.read_frame(&mut scene).unwrap());
+
+        let mut mse = MeanSquaredError::default();
+        assert_eq!(mse.get_value(), 0.0);
+        mse.update(&video_frames).unwrap();
+        assert!((mse.get_value() - 2.0).abs() < 0.1, "unexpected MSE {}", mse.get_value());
+        assert!(mse.get_value_avg() > 0.0);
+    }
+}
@@ -1,5 +1,19 @@
-#[allow(dead_code)]
+//! Motion detectors, run as a configurable cascade of increasing cost: by default a cheap mean
+//! squared error between consecutive frames gates the more expensive MOG2 (or KNN) background
+//! subtraction, whose motion area is then debounced into motion events. Each implements
+//! [`Detector`](detector::Detector), so that any of them can fill any stage.
+
+pub mod detector;
+
 pub mod mean_squared_error;
 
-#[allow(dead_code)]
-pub mod motion_mog2;
\ No newline at end of file
+pub mod foreground_filter;
+
+pub mod motion_mog2;
+pub mod motion_mog2_params;
+
+pub mod motion_knn;
+
+pub mod cascade;
+
+pub mod motion_events;
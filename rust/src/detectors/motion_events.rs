@@ -0,0 +1,194 @@
+use crate::config::MotionEventSettings;
+
+/// A change of the debounced motion state.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MotionEvent {
+    MotionStarted {
+        frame: u64,
+        timestamp_ms: f64,
+    },
+    MotionEnded {
+        frame: u64,
+        timestamp_ms: f64,
+        duration_ms: f64,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum State {
+    Idle,
+    Rising { since_ms: f64, frames: u32 },      // motion seen, not yet for long enough
+    Active,
+    Falling { since_ms: f64, frames: u32 },     // motion gone, not yet for long enough
+}
+
+/// Turns the per frame MOG2 motion area into discrete motion events. An event starts once
+/// the area has stayed at or above the start threshold for the minimum on duration and ends
+/// once it has stayed below the (lower) stop threshold for the minimum off duration. After an
+/// event ends, no new one starts until the cooldown has passed.
+pub struct MotionEvents {
+    start_threshold: f64,
+    stop_threshold: f64,
+    settings: MotionEventSettings,
+    state: State,
+    started_ms: f64,
+    cooldown_until_ms: f64,
+    cooldown_frames: u32,               // processed frames left in the cooldown
+}
+
+impl MotionEvents {
+    pub fn new(start_threshold: f64, settings: &MotionEventSettings) -> Self {
+        // a negative stop threshold disables the hysteresis
+        let stop_threshold = if settings.stop_threshold < 0.0 { start_threshold } else { settings.stop_threshold };
+        Self {
+            start_threshold,
+            stop_threshold,
+            settings: settings.clone(),
+            state: State::Idle,
+            started_ms: 0.0,
+            cooldown_until_ms: f64::MIN,
+            cooldown_frames: 0,
+        }
+    }
+
+    pub fn is_active(&self) -> bool {
+        matches!(self.state, State::Active | State::Falling { .. })
+    }
+
+    // the area is None for frames in which the MSE gate kept MOG2 from running
+    pub fn update(&mut self, frame: u64, timestamp_ms: f64, area: Option<f64>) -> Option<MotionEvent> {
+        let area = area.unwrap_or(0.0);
+        let settings = &self.settings;
+
+        match self.state {
+            State::Idle | State::Rising { .. } if area < self.start_threshold => {
+                self.state = State::Idle;
+            }
+            State::Idle => {
+                if self.cooldown_frames == 0 && timestamp_ms >= self.cooldown_until_ms {
+                    self.state = State::Rising { since_ms: timestamp_ms, frames: 0 };
+                    return self.update(frame, timestamp_ms, Some(area));
+                }
+            }
+            State::Rising { since_ms, frames } => {
+                let frames = frames + 1;
+                self.state = State::Rising { since_ms, frames };
+                if frames >= settings.min_on_frames && timestamp_ms - since_ms >= settings.min_on_ms as f64 {
+                    self.state = State::Active;
+                    self.started_ms = timestamp_ms;
+                    return Some(MotionEvent::MotionStarted { frame, timestamp_ms });
+                }
+            }
+            State::Active | State::Falling { .. } if area >= self.stop_threshold => {
+                self.state = State::Active;
+            }
+            State::Active => {
+                self.state = State::Falling { since_ms: timestamp_ms, frames: 0 };
+                return self.update(frame, timestamp_ms, Some(area));
+            }
+            State::Falling { since_ms, frames } => {
+                let frames = frames + 1;
+                self.state = State::Falling { since_ms, frames };
+                if frames >= settings.min_off_frames && timestamp_ms - since_ms >= settings.min_off_ms as f64 {
+                    return Some(self.end(frame, timestamp_ms));
+                }
+            }
+        }
+
+        if self.state == State::Idle {
+            self.cooldown_frames = self.cooldown_frames.saturating_sub(1);
+        }
+        None
+    }
+
+    // ends an event that is still going when its source runs out
+    pub fn finish(&mut self, frame: u64, timestamp_ms: f64) -> Option<MotionEvent> {
+        if self.is_active() {
+            Some(self.end(frame, timestamp_ms))
+        } else {
+            self.state = State::Idle;
+            None
+        }
+    }
+
+    fn end(&mut self, frame: u64, timestamp_ms: f64) -> MotionEvent {
+        self.state = State::Idle;
+        self.cooldown_until_ms = timestamp_ms + self.settings.cooldown_ms as f64;
+        self.cooldown_frames = self.settings.cooldown_frames;
+        MotionEvent::MotionEnded {
+            frame,
+            timestamp_ms,
+            duration_ms: timestamp_ms - self.started_ms,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // feeds one area per processed frame at 10 fps, returning the frames that raised an event
+    fn run(events: &mut MotionEvents, areas: &[f64]) -> Vec<(u64, MotionEvent)> {
+        let mut raised = Vec::new();
+        for (index, area) in areas.iter().enumerate() {
+            let frame = index as u64;
+            if let Some(event) = events.update(frame, frame as f64 * 100.0, Some(*area)) {
+                raised.push((frame, event));
+            }
+        }
+        if let Some(event) = events.finish(areas.len() as u64, areas.len() as f64 * 100.0) {
+            raised.push((areas.len() as u64, event));
+        }
+        raised
+    }
+
+    fn frames(raised: &[(u64, MotionEvent)]) -> Vec<u64> {
+        raised.iter().map(|(frame, _)| *frame).collect()
+    }
+
+    #[test]
+    fn defaults_follow_every_frame() {
+        let mut events = MotionEvents::new(100.0, &MotionEventSettings::default());
+        let raised = run(&mut events, &[0.0, 150.0, 150.0, 50.0, 150.0]);
+        assert_eq!(frames(&raised), vec![1, 3, 4, 5]);
+        assert_eq!(raised[1].1, MotionEvent::MotionEnded { frame: 3, timestamp_ms: 300.0, duration_ms: 200.0 });
+    }
+
+    #[test]
+    fn hysteresis_keeps_an_event_going_between_the_thresholds() {
+        let settings = MotionEventSettings {
+            stop_threshold: 40.0,
+            ..MotionEventSettings::default()
+        };
+        let mut events = MotionEvents::new(100.0, &settings);
+        // 60 neither starts an event nor ends one
+        let raised = run(&mut events, &[60.0, 150.0, 60.0, 60.0, 30.0, 60.0]);
+        assert_eq!(frames(&raised), vec![1, 4]);
+    }
+
+    #[test]
+    fn short_motion_and_short_gaps_are_ignored() {
+        let settings = MotionEventSettings {
+            min_on_frames: 3,
+            min_off_ms: 200,
+            ..MotionEventSettings::default()
+        };
+        let mut events = MotionEvents::new(100.0, &settings);
+        let raised = run(&mut events, &[150.0, 150.0, 0.0, 150.0, 150.0, 150.0, 0.0, 0.0, 150.0, 0.0, 0.0, 0.0, 0.0]);
+        // the first two frames of motion are too few, the gap at 6-7 too short
+        assert_eq!(frames(&raised), vec![5, 11]);
+        assert_eq!(raised[1].1, MotionEvent::MotionEnded { frame: 11, timestamp_ms: 1100.0, duration_ms: 600.0 });
+    }
+
+    #[test]
+    fn cooldown_holds_off_the_next_event() {
+        let settings = MotionEventSettings {
+            cooldown_ms: 300,
+            ..MotionEventSettings::default()
+        };
+        let mut events = MotionEvents::new(100.0, &settings);
+        let raised = run(&mut events, &[150.0, 0.0, 150.0, 150.0, 150.0, 150.0]);
+        // ended at 100 ms, so the motion at 200 and 300 ms is ignored
+        assert_eq!(frames(&raised), vec![0, 1, 4, 6]);
+    }
+}
@@ -0,0 +1,105 @@
+use opencv::core::{Mat, Ptr, Rect};
+use opencv::Error;
+use crate::config::KnnSettings;
+use crate::detectors::detector::{Detection, Detector};
+use crate::detectors::foreground_filter::ForegroundFilter;
+use crate::detectors::motion_mog2_params::MotionMog2Params;
+use crate::error::{self, VisionError};
+use crate::util::video_frames::{FrameProcessor, Tier, VideoFrames};
+
+/// KNN background subtraction followed by the same [`ForegroundFilter`] post-processing as
+/// [`MotionMog2`](crate::detectors::motion_mog2::MotionMog2), so that the two can be compared
+/// on the same footage.
+pub struct MotionKnn {
+
+    params: KnnSettings,
+    tier: Tier,                     // the monochrome samples the detector runs on
+
+    bg_remover: Ptr<opencv::video::BackgroundSubtractorKNN>,
+
+    filter: ForegroundFilter,
+}
+
+impl MotionKnn {
+    // the post-processing is taken from `filter`, e.g. the `[mog2]` section of the settings
+    pub fn new(prv_frame: &Mat, params: KnnSettings, filter: &MotionMog2Params) -> error::Result<Self> {
+        Ok(Self {
+            bg_remover: opencv::video::create_background_subtractor_knn(
+                params.history,
+                params.dist2_threshold,
+                false
+            )?,
+            filter: ForegroundFilter::new(prv_frame, filter)?,
+            params,
+            tier: Tier::Quarter,
+        })
+    }
+
+    pub fn params(&self) -> &KnnSettings {
+        &self.params
+    }
+
+    // runs on the samples of another tier, which `prv_frame` should have been taken from
+    pub fn with_tier(mut self, tier: Tier) -> Self {
+        self.tier = tier;
+        self
+    }
+
+    // a binary mask at the detector's tier, outside of which motion is ignored
+    pub fn set_mask(&mut self, mask: Option<Mat>) {
+        self.filter.set_mask(mask);
+    }
+
+    pub fn get_foreground(&self) -> &Mat {
+        self.filter.get_foreground()
+    }
+
+    pub fn get_diff_mask(&self) -> &Mat {
+        self.filter.get_diff_mask()
+    }
+
+    pub fn get_bounding_boxes(&self) -> &Vec<Rect> {
+        self.filter.get_bounding_boxes()
+    }
+
+    fn process_frame(&mut self, cur_frame: &Mat) -> Result<(), Error> {
+        self.filter.apply(&mut self.bg_remover, cur_frame, self.params.learning_rate)
+    }
+
+    pub fn get_area(&self) -> f64 {
+        self.filter.get_area()
+    }
+
+    pub fn get_area_avg(&mut self) -> error::Result<f64> {
+        self.filter.calc_total_area().map_err(VisionError::detector("KNN"))?;
+        Ok(self.filter.area_avg())
+    }
+}
+
+impl<'a> FrameProcessor<'a> for MotionKnn {
+    fn update(&mut self, video_frames: &VideoFrames) -> error::Result<()> {
+        self.process_frame(&video_frames.mono.tier(self.tier).cur)
+            .map_err(VisionError::detector("KNN"))
+    }
+}
+
+impl Detector for MotionKnn {
+    fn name(&self) -> &str {
+        "KNN"
+    }
+
+    fn tier(&self) -> Tier {
+        self.tier
+    }
+
+    fn detect(&mut self, video_frames: &VideoFrames) -> error::Result<Detection<'_>> {
+        self.update(video_frames)?;
+        self.filter.calc_total_area().map_err(VisionError::detector("KNN"))?;
+        Ok(self.filter.detection(self.tier))
+    }
+
+    fn set_mask(&mut self, mask: Option<Mat>) {
+        MotionKnn::set_mask(self, mask);
+    }
+}
+
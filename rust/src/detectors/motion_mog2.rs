@@ -1,75 +1,23 @@
-use opencv::core::{
-    self,
-    Mat,
-    MatExpr,
-    MatExprTraitConst,
-    MatTraitConst,
-    Point,
-    Vec4i,
-    Vector,
-    Scalar,
-    Ptr,
-};
-use opencv::imgproc::{self, adaptive_threshold, erode, morphology_ex, dilate, find_contours, draw_contours, bounding_rect};
+use opencv::core::{Mat, Point, Ptr, Rect, Vector};
 use opencv::Error;
 use opencv::hub_prelude::BackgroundSubtractorMOG2Trait;
-use crate::util::video_frames::{FrameProcessor, VideoFrames};
-
-const SAMPLE_COUNT: usize = 10;
-
+use crate::detectors::detector::{Detection, Detector};
+use crate::detectors::foreground_filter::ForegroundFilter;
+use crate::detectors::motion_mog2_params::{MotionMog2Builder, MotionMog2Params};
+use crate::error::{self, VisionError};
+use crate::util::video_frames::{FrameProcessor, Tier, VideoFrames};
+
+/// MOG2 background subtraction followed by the [`ForegroundFilter`] post-processing, yielding a
+/// motion mask, bounding boxes and the total motion area, optionally without cast shadows.
 pub struct MotionMog2 {
 
-    bg_remover: Ptr<opencv::video::BackgroundSubtractorMOG2>,
-
-    diff_mask: Mat,                 // MOG2 motion mask (source for frame copy operations)
-    dst_frame: Mat,                 // destination for frame copy operations
-
-    default_scalar: Scalar,
+    params: MotionMog2Params,
+    tier: Tier,                     // the monochrome samples the detector runs on
 
+    bg_remover: Ptr<opencv::video::BackgroundSubtractorMOG2>,
     mog2_learning_rate: f64,
 
-    adaptive_max_value: f64,
-    adaptive_method: i32,
-    adaptive_type: i32,
-    adaptive_block_size: i32,
-    adaptive_c: f64,
-
-    erode_iterations: i32,
-    erode_border_type: i32,
-    erode_kernel: MatExpr,
-    erode_anchor_point: Point,
-
-    dilate_iterations: i32,
-    dilate_border_type: i32,
-    dilate_kernel: MatExpr,
-    dilate_anchor_point: Point,
-
-    close_operation: i32,
-    close_iterations: i32,
-    close_border_type: i32,
-    close_kernel: MatExpr,
-    close_anchor_point: Point,
-
-    contour_mode: i32,
-    contour_method: i32,
-    contours: Vector<Vector<Point>>,
-    contour_anchor_point: Point,
-    contour_fill_anchor_point: Point,
-    contour_index: i32,
-    contour_thickness: i32,
-    contour_line_type: i32,
-    contour_max_level: i32,
-    contour_color: Scalar,
-
-    bounding_boxes: Vec<core::Rect>,
-    bounding_box_min_area: i32,
-
-    mog2_values: Vec<f64>,
-
-    _total_area: i32,
-    _contour_area: i32,
-    _contour_p1: Point,
-    _contour_p2: Point,
+    filter: ForegroundFilter,
 }
 
 impl<'a> MotionMog2 {
@@ -79,296 +27,178 @@ impl<'a> MotionMog2 {
         var_threshold: f64,
         adaptive_block_size: i32,
         adaptive_c: f64,
-    ) -> Self {
-        Self {
-            diff_mask: Mat::zeros(
-                prv_frame.rows(),
-                prv_frame.cols(),
-                prv_frame.typ(),
-            ).unwrap().to_mat().unwrap(),
-            dst_frame: Mat::zeros(
-                prv_frame.rows(),
-                prv_frame.cols(),
-                prv_frame.typ(),
-            ).unwrap().to_mat().unwrap(),
-
-            default_scalar: Scalar::default(),
-
-            mog2_learning_rate: -1.0,
-            bg_remover: opencv::video::create_background_subtractor_mog2(
-                history,
-                var_threshold,
-                false
-            ).unwrap(),
-
-            adaptive_max_value: 255.0,
-            adaptive_method: imgproc::ADAPTIVE_THRESH_GAUSSIAN_C,
-            adaptive_type: imgproc::THRESH_BINARY_INV,
-            adaptive_block_size,
-            adaptive_c,
-
-            erode_kernel: Mat::ones(
-                2,
-                2,
-                core::CV_8U
-            ).unwrap(),
-            erode_anchor_point: Point::new(-1, -1),
-            erode_iterations: 1,
-            erode_border_type: core::BORDER_CONSTANT,
-
-            dilate_kernel: Mat::ones(
-                2,
-                2,
-                core::CV_8U,
-            ).unwrap(),
-            dilate_anchor_point: Point::new(-1, -1),
-            dilate_iterations: 1,
-            dilate_border_type: core::BORDER_CONSTANT,
-
-            close_operation: imgproc::MORPH_CLOSE,
-            close_kernel: Mat::ones(
-                3,
-                3,
-                core::CV_8U,
-            ).unwrap(),
-            close_anchor_point: Point::new(-1, -1),
-            close_iterations: 1,
-            close_border_type: core::BORDER_CONSTANT,
-
-            contour_mode: imgproc::RETR_EXTERNAL,
-            contour_method: imgproc::CHAIN_APPROX_SIMPLE,
-            contours: Vector::new(),
-            contour_color: Scalar::new(
-                255.0,
-                255.0,
-                255.0,
-                0.0
-            ),
-            contour_anchor_point: Point::new(-1, -1),
-            contour_index: -1,
-            contour_thickness: imgproc::FILLED,
-            contour_line_type: imgproc::LINE_8,
-            contour_max_level: 0,
-            contour_fill_anchor_point: Point::new(-1, -1),
-
-            bounding_boxes: Vec::with_capacity(10),
-            bounding_box_min_area: 500,
-
-            mog2_values: vec![0.0; SAMPLE_COUNT],
-
-            _total_area: 0,
-            _contour_area: 0,
-            _contour_p1: Point::new(-1, -1),
-            _contour_p2: Point::new(-1, -1),
-        }
+    ) -> error::Result<Self> {
+        Self::builder()
+            .with_history(history)
+            .with_var_threshold(var_threshold)
+            .with_adaptive_threshold(adaptive_block_size, adaptive_c)
+            .build(prv_frame)
     }
 
-    pub fn default() -> Self {
-        Self {
-
-            diff_mask: Mat::default(),
-            dst_frame: Mat::default(),
-
-            default_scalar: Scalar::default(),
-
-            mog2_learning_rate: -1.0,
-            bg_remover: opencv::video::create_background_subtractor_mog2(
-                0,
-                0.0,
-                false
-            ).unwrap(),
-
-            adaptive_max_value: 255.0,
-            adaptive_method: imgproc::ADAPTIVE_THRESH_GAUSSIAN_C,
-            adaptive_type: imgproc::THRESH_BINARY_INV,
-            adaptive_block_size: 17,
-            adaptive_c: 9.0,
-
-            erode_kernel: Mat::ones(
-                2,
-                2,
-                core::CV_8U
-            ).unwrap(),
-            erode_anchor_point: Point::new(-1, -1),
-            erode_iterations: 1,
-            erode_border_type: core::BORDER_CONSTANT,
-
-            dilate_kernel: Mat::ones(
-                2,
-                2,
-                core::CV_8U,
-            ).unwrap(),
-            dilate_anchor_point: Point::new(-1, -1),
-            dilate_iterations: 1,
-            dilate_border_type: core::BORDER_CONSTANT,
-
-            close_operation: imgproc::MORPH_CLOSE,
-            close_kernel: Mat::ones(
-                3,
-                3,
-                core::CV_8U,
-            ).unwrap(),
-            close_anchor_point: Point::new(-1, -1),
-            close_iterations: 1,
-            close_border_type: core::BORDER_CONSTANT,
-
-            contour_mode: imgproc::RETR_EXTERNAL,
-            contour_method: imgproc::CHAIN_APPROX_SIMPLE,
-            contours: Vector::new(),
-            contour_color: Scalar::new(
-                255.0,
-                255.0,
-                255.0,
-                0.0
-            ),
-            contour_anchor_point: Point::new(-1, -1),
-            contour_index: -1,
-            contour_thickness: imgproc::FILLED,
-            contour_line_type: imgproc::LINE_8,
-            contour_max_level: 0,
-            contour_fill_anchor_point: Point::new(-1, -1),
-
-            bounding_boxes: Vec::with_capacity(10),
-            bounding_box_min_area: 500,
-
-            mog2_values: vec![0.0; SAMPLE_COUNT],
-
-            _total_area: 0,
-            _contour_area: 0,
-            _contour_p1: Point::new(-1, -1),
-            _contour_p2: Point::new(-1, -1),
-        }
+    pub fn builder() -> MotionMog2Builder {
+        MotionMog2Builder::new()
+    }
+
+    pub fn from_params(prv_frame: &'a Mat, params: MotionMog2Params) -> error::Result<Self> {
+        params.validate()?;
+        let filter = ForegroundFilter::new(prv_frame, &params)?;
+        Ok(Self {
+            mog2_learning_rate: params.learning_rate,
+            bg_remover: background_subtractor(&params)?,
+
+            filter: if params.detect_shadows { filter.with_shadows(params.shadow_value) } else { filter },
+
+            params,
+            tier: Tier::Quarter,
+        })
+    }
+
+    pub fn params(&self) -> &MotionMog2Params {
+        &self.params
+    }
+
+    // runs on the samples of another tier, which `prv_frame` should have been taken from
+    pub fn with_tier(mut self, tier: Tier) -> Self {
+        self.tier = tier;
+        self
+    }
+
+    // a binary mask at the detector's tier, outside of which motion is ignored
+    pub fn set_mask(&mut self, mask: Option<Mat>) {
+        self.filter.set_mask(mask);
+    }
+
+    // the binary motion mask of the latest frame, before the mask was applied
+    pub fn get_foreground(&self) -> &Mat {
+        self.filter.get_foreground()
     }
 
     pub fn get_diff_mask(&self) -> &Mat {
-        &self.diff_mask
+        self.filter.get_diff_mask()
+    }
+
+    // the shadows of the latest frame, None unless `detect_shadows` is set
+    pub fn get_shadows(&self) -> Option<&Mat> {
+        self.filter.get_shadows()
+    }
+
+    pub fn shadow_fraction(&self) -> Option<f64> {
+        self.filter.shadow_fraction()
     }
 
     pub fn get_contours(&self) -> &Vector<Vector<Point>> {
-        &self.contours
+        self.filter.get_contours()
     }
 
     pub fn get_values(&self) -> &Vec<f64> {
-        &self.mog2_values
+        self.filter.get_values()
     }
 
-    pub fn get_bounding_boxes(&self) -> &Vec<core::Rect> {
-        &self.bounding_boxes
+    pub fn get_bounding_boxes(&self) -> &Vec<Rect> {
+        self.filter.get_bounding_boxes()
     }
 
     fn process_frame(&mut self, cur_frame: &'a Mat) -> Result<(), Error> {
+        self.filter.apply(&mut self.bg_remover, cur_frame, self.mog2_learning_rate)
+    }
 
-        self.contours.clear();
-
-        self.bg_remover.apply(
-            cur_frame,
-            &mut self.diff_mask,
-            self.mog2_learning_rate
-        )?;
-
-        adaptive_threshold(
-            &self.diff_mask,
-            &mut self.dst_frame,
-            self.adaptive_max_value,
-            self.adaptive_method,
-            self.adaptive_type,
-            self.adaptive_block_size,
-            self.adaptive_c,
-        )?;
-
-        erode(
-            &self.dst_frame,
-            &mut self.diff_mask,
-            &self.erode_kernel,
-            self.erode_anchor_point,
-            self.erode_iterations,
-            self.erode_border_type,
-            self.default_scalar,
-        )?;
-
-        dilate(
-            &self.dst_frame,
-            &mut self.diff_mask,
-            &self.dilate_kernel,
-            self.dilate_anchor_point,
-            self.dilate_iterations,
-            self.dilate_border_type,
-            self.default_scalar,
-        )?;
-
-        morphology_ex(
-            &self.diff_mask,
-            &mut self.dst_frame,
-            self.close_operation,
-            &self.close_kernel,
-            self.close_anchor_point,
-            self.close_iterations,
-            self.close_border_type,
-            self.default_scalar,
-        )?;
-
-        find_contours(
-            &self.dst_frame,
-            &mut self.contours,
-            self.contour_mode,
-            self.contour_method,
-            self.contour_anchor_point,
-        )?;
-
-        // filter bounding boxes
-        self.bounding_boxes = self.contours.iter()
-            .filter_map(|c| bounding_rect(&c).ok())
-            .filter(|rect| rect.area() >= self.bounding_box_min_area)
-            .collect();
-
-        // Sort rectangles by area (largest first) to optimize nesting check
-        self.bounding_boxes.sort_unstable_by_key(|rect| -(rect.area() as i64));
-
-        draw_contours(
-            &mut self.diff_mask,
-            &self.contours,
-            self.contour_index,
-            self.contour_color,
-            self.contour_thickness,
-            self.contour_line_type,
-            &Vector::<Vec4i>::new(),
-            self.contour_max_level,
-            self.contour_fill_anchor_point,
-        )?;
-
-        Ok(())
+    pub fn get_area(&self) -> f64 {
+        self.filter.get_area()
     }
 
-    fn _calc_total_area(&mut self) -> Result<(), Error> {
-        self._total_area = 0;
-        self._contour_area = 0;
-        for index in 0..self.contours.len() {
-            if let Ok(contour) = self.contours.get(index) {
-                for i in 0..contour.len() {
-                    self._contour_p1 = contour.get(i)?;
-                    self._contour_p2 = contour.get((i + 1) % contour.len())?;
-                    self._contour_area += (self._contour_p1.x - self._contour_p2.x) * (self._contour_p1.y + self._contour_p2.y);
-                }
-                self. _total_area += self._contour_area.abs();
-            }
-        }
-        self.mog2_values.remove(0);
-        self.mog2_values.push(self._total_area as f64);
-        Ok(())
+    pub fn get_area_avg(&mut self) -> error::Result<f64> {
+        self.filter.calc_total_area().map_err(VisionError::detector("MOG2"))?;
+        Ok(self.filter.area_avg())
     }
+}
 
-    pub fn get_area(&self) -> f64 {
-        self.mog2_values[SAMPLE_COUNT - 1]
+// the subtractor marks shadows only if asked to, with the configured value and threshold
+fn background_subtractor(params: &MotionMog2Params) -> opencv::Result<Ptr<opencv::video::BackgroundSubtractorMOG2>> {
+    let mut bg_remover = opencv::video::create_background_subtractor_mog2(
+        params.history,
+        params.var_threshold,
+        params.detect_shadows
+    )?;
+    bg_remover.set_shadow_value(params.shadow_value)?;
+    bg_remover.set_shadow_threshold(params.shadow_threshold)?;
+    Ok(bg_remover)
+}
+
+impl<'a> FrameProcessor<'a> for MotionMog2 {
+    fn update(&mut self, video_frames: &VideoFrames) -> error::Result<()> {
+        self.process_frame(&video_frames.mono.tier(self.tier).cur)
+            .map_err(VisionError::detector("MOG2"))
+    }
+}
+
+impl Detector for MotionMog2 {
+    fn name(&self) -> &str {
+        "MOG2"
     }
 
-    pub fn get_area_avg(&mut self) -> f64 {
-        self._calc_total_area().unwrap();
-        self.mog2_values.iter().sum::<f64>() / SAMPLE_COUNT as f64
+    fn tier(&self) -> Tier {
+        self.tier
+    }
+
+    // scored by the average motion area, which is only added to the window here
+    fn detect(&mut self, video_frames: &VideoFrames) -> error::Result<Detection<'_>> {
+        self.update(video_frames)?;
+        self.filter.calc_total_area().map_err(VisionError::detector("MOG2"))?;
+        Ok(self.filter.detection(self.tier))
+    }
+
+    fn set_mask(&mut self, mask: Option<Mat>) {
+        MotionMog2::set_mask(self, mask);
     }
 }
 
-impl<'a> FrameProcessor<'a> for MotionMog2 {
-    fn update(&mut self, video_frames: &VideoFrames) -> Result<(), Error> {
-        self.process_frame(&video_frames.mono.quarter.cur)
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use opencv::core::{self, Scalar, Size};
+    use opencv::imgproc;
+    use crate::sources::synthetic_scene::{Shape, SyntheticScene};
+
+    fn detect_with(mut scene: SyntheticScene, builder: MotionMog2Builder, mask: Option<Mat>) -> MotionMog2 {
+        let mut video_frames = VideoFrames::new(
+            Size::new(1280, 720),
+            Size::new(640, 360),
+            Size::new(320, 180),
+        ).unwrap();
+        assert!(video_frames.read_frame(&mut scene).unwrap());
+        let mut mog2 = builder.build(&video_frames.mono.quarter.cur).unwrap();
+        mog2.set_mask(mask);
+        while video_frames.read_frame(&mut scene).unwrap() {
+            mog2.update(&video_frames).unwrap();
+        }
+        mog2
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn masked_motion_is_ignored() {
+        // the object moves through the lower two thirds of the frame, which are masked out
+        let mut mask = Mat::new_size_with_default(Size::new(320, 180), core::CV_8UC1, Scalar::all(255.0)).unwrap();
+        imgproc::rectangle(&mut mask, Rect::new(0, 60, 320, 120), Scalar::all(0.0), imgproc::FILLED, imgproc::LINE_8, 0).unwrap();
+
+        let mut mog2 = detect_with(SyntheticScene::fixture(Some(Shape::Rectangle)), MotionMog2::builder(), Some(mask));
+        assert!(mog2.get_bounding_boxes().is_empty());
+        assert_eq!(mog2.get_area_avg().unwrap(), 0.0);
+        assert!(core::count_non_zero(mog2.get_foreground()).unwrap() > 0);
+    }
+
+    #[test]
+    fn shadows_are_left_out_of_the_area() {
+        let mut mog2 = detect_with(SyntheticScene::fixture(Some(Shape::Shadow)), MotionMog2::builder(), None);
+        assert!(mog2.get_shadows().is_none());
+        assert!(mog2.get_area_avg().unwrap() > 0.0);
+
+        let mut mog2 = detect_with(
+            SyntheticScene::fixture(Some(Shape::Shadow)),
+            MotionMog2::builder().with_shadows(127, 0.5),
+            None,
+        );
+        assert!(core::count_non_zero(mog2.get_shadows().expect("shadows not detected")).unwrap() > 0);
+        assert!(mog2.shadow_fraction().unwrap() > 0.5, "{:?}", mog2.shadow_fraction());
+        assert!(mog2.get_area_avg().unwrap() < 100.0);
+    }
+}
@@ -0,0 +1,307 @@
+use opencv::core::{self, Mat};
+use opencv::imgproc;
+use serde::{Deserialize, Serialize};
+use crate::detectors::motion_mog2::MotionMog2;
+use crate::error::{Result, VisionError};
+
+/// How the morphology operations extrapolate pixels beyond the image border.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BorderType {
+    Constant,
+    Replicate,
+    Reflect,
+    Reflect101,
+}
+
+impl BorderType {
+    pub fn to_cv(self) -> i32 {
+        match self {
+            BorderType::Constant => core::BORDER_CONSTANT,
+            BorderType::Replicate => core::BORDER_REPLICATE,
+            BorderType::Reflect => core::BORDER_REFLECT,
+            BorderType::Reflect101 => core::BORDER_REFLECT_101,
+        }
+    }
+}
+
+/// Which contours are retrieved from the motion mask.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ContourMode {
+    External,
+    List,
+    #[serde(rename = "ccomp")]
+    CComp,
+    Tree,
+}
+
+impl ContourMode {
+    pub fn to_cv(self) -> i32 {
+        match self {
+            ContourMode::External => imgproc::RETR_EXTERNAL,
+            ContourMode::List => imgproc::RETR_LIST,
+            ContourMode::CComp => imgproc::RETR_CCOMP,
+            ContourMode::Tree => imgproc::RETR_TREE,
+        }
+    }
+}
+
+/// How contour points are compressed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ContourApproximation {
+    None,
+    Simple,
+    Tc89L1,
+    Tc89Kcos,
+}
+
+impl ContourApproximation {
+    pub fn to_cv(self) -> i32 {
+        match self {
+            ContourApproximation::None => imgproc::CHAIN_APPROX_NONE,
+            ContourApproximation::Simple => imgproc::CHAIN_APPROX_SIMPLE,
+            ContourApproximation::Tc89L1 => imgproc::CHAIN_APPROX_TC89_L1,
+            ContourApproximation::Tc89Kcos => imgproc::CHAIN_APPROX_TC89_KCOS,
+        }
+    }
+}
+
+/// Every tunable of [`MotionMog2`], serialisable so that settings can be kept per camera.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MotionMog2Params {
+    pub threshold: f64,                 // average motion area that counts as motion
+    pub history: i32,
+    pub var_threshold: f64,
+    pub learning_rate: f64,             // negative => chosen by OpenCV from the history
+    pub detect_shadows: bool,           // shadow pixels are left out of the motion area
+    pub shadow_value: i32,              // the value MOG2 marks shadow pixels with
+    pub shadow_threshold: f64,          // how much darker than the background a shadow may be, 0.5 => up to half as bright
+    pub adaptive_block_size: i32,
+    pub adaptive_c: f64,
+    pub erode_kernel: [i32; 2],         // [width, height]
+    pub erode_iterations: i32,
+    pub erode_border: BorderType,
+    pub dilate_kernel: [i32; 2],
+    pub dilate_iterations: i32,
+    pub dilate_border: BorderType,
+    pub close_kernel: [i32; 2],
+    pub close_iterations: i32,
+    pub close_border: BorderType,
+    pub contour_mode: ContourMode,
+    pub contour_approximation: ContourApproximation,
+    pub min_box_area: i32,              // smaller bounding boxes are dropped
+    pub window: usize,                  // frames the motion area is averaged over
+}
+
+impl Default for MotionMog2Params {
+    fn default() -> Self {
+        Self {
+            threshold: 5000.0,
+            history: 500,
+            var_threshold: 50.0,
+            learning_rate: -1.0,
+            detect_shadows: false,
+            shadow_value: 127,
+            shadow_threshold: 0.5,
+            adaptive_block_size: 17,
+            adaptive_c: 9.0,
+            erode_kernel: [2, 2],
+            erode_iterations: 1,
+            erode_border: BorderType::Constant,
+            dilate_kernel: [2, 2],
+            dilate_iterations: 1,
+            dilate_border: BorderType::Constant,
+            close_kernel: [3, 3],
+            close_iterations: 1,
+            close_border: BorderType::Constant,
+            contour_mode: ContourMode::External,
+            contour_approximation: ContourApproximation::Simple,
+            min_box_area: 500,
+            window: 10,
+        }
+    }
+}
+
+impl MotionMog2Params {
+    pub fn validate(&self) -> Result<()> {
+        let problems = self.problems("");
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(VisionError::InvalidConfig(problems.join("; ")))
+        }
+    }
+
+    // every out of range parameter, each named with the given prefix (e.g. "mog2.")
+    pub(crate) fn problems(&self, prefix: &str) -> Vec<String> {
+        let mut problems = Vec::new();
+        let mut check = |valid: bool, problem: String| {
+            if !valid {
+                problems.push(format!("{}{}", prefix, problem));
+            }
+        };
+
+        check(self.threshold >= 0.0, format!("threshold must not be negative, got {}", self.threshold));
+        check(self.history > 0, format!("history must be positive, got {}", self.history));
+        check(self.var_threshold > 0.0, format!("var_threshold must be positive, got {}", self.var_threshold));
+        check(self.learning_rate <= 1.0, format!("learning_rate must not exceed 1, got {}", self.learning_rate));
+        check(
+            (1..=254).contains(&self.shadow_value),
+            format!("shadow_value must be between 1 and 254, got {}", self.shadow_value),
+        );
+        check(
+            self.shadow_threshold > 0.0 && self.shadow_threshold <= 1.0,
+            format!("shadow_threshold must be above 0 and at most 1, got {}", self.shadow_threshold),
+        );
+        check(
+            self.adaptive_block_size >= 3 && self.adaptive_block_size % 2 == 1,
+            format!("adaptive_block_size must be an odd number of at least 3, got {}", self.adaptive_block_size),
+        );
+        for (name, kernel, iterations) in [
+            ("erode", self.erode_kernel, self.erode_iterations),
+            ("dilate", self.dilate_kernel, self.dilate_iterations),
+            ("close", self.close_kernel, self.close_iterations),
+        ] {
+            check(kernel.iter().all(|d| *d > 0), format!("{}_kernel must be positive, got {:?}", name, kernel));
+            check(iterations >= 0, format!("{}_iterations must not be negative, got {}", name, iterations));
+        }
+        check(self.min_box_area >= 0, format!("min_box_area must not be negative, got {}", self.min_box_area));
+        check(self.window > 0, String::from("window must be at least 1"));
+
+        problems
+    }
+}
+
+/// Builds a [`MotionMog2`], checking the parameters before any OpenCV state is created.
+#[derive(Debug, Clone, Default)]
+pub struct MotionMog2Builder {
+    params: MotionMog2Params,
+}
+
+impl MotionMog2Builder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn from_params(params: MotionMog2Params) -> Self {
+        Self { params }
+    }
+
+    pub fn with_threshold(mut self, threshold: f64) -> Self {
+        self.params.threshold = threshold;
+        self
+    }
+
+    pub fn with_history(mut self, history: i32) -> Self {
+        self.params.history = history;
+        self
+    }
+
+    pub fn with_var_threshold(mut self, var_threshold: f64) -> Self {
+        self.params.var_threshold = var_threshold;
+        self
+    }
+
+    pub fn with_learning_rate(mut self, learning_rate: f64) -> Self {
+        self.params.learning_rate = learning_rate;
+        self
+    }
+
+    // detects shadows, marked with `value`, so that they can be left out of the motion area
+    pub fn with_shadows(mut self, value: i32, threshold: f64) -> Self {
+        self.params.detect_shadows = true;
+        self.params.shadow_value = value;
+        self.params.shadow_threshold = threshold;
+        self
+    }
+
+    pub fn with_adaptive_threshold(mut self, block_size: i32, c: f64) -> Self {
+        self.params.adaptive_block_size = block_size;
+        self.params.adaptive_c = c;
+        self
+    }
+
+    pub fn with_erode(mut self, kernel: [i32; 2], iterations: i32, border: BorderType) -> Self {
+        self.params.erode_kernel = kernel;
+        self.params.erode_iterations = iterations;
+        self.params.erode_border = border;
+        self
+    }
+
+    pub fn with_dilate(mut self, kernel: [i32; 2], iterations: i32, border: BorderType) -> Self {
+        self.params.dilate_kernel = kernel;
+        self.params.dilate_iterations = iterations;
+        self.params.dilate_border = border;
+        self
+    }
+
+    pub fn with_close(mut self, kernel: [i32; 2], iterations: i32, border: BorderType) -> Self {
+        self.params.close_kernel = kernel;
+        self.params.close_iterations = iterations;
+        self.params.close_border = border;
+        self
+    }
+
+    pub fn with_contours(mut self, mode: ContourMode, approximation: ContourApproximation) -> Self {
+        self.params.contour_mode = mode;
+        self.params.contour_approximation = approximation;
+        self
+    }
+
+    pub fn with_min_box_area(mut self, min_box_area: i32) -> Self {
+        self.params.min_box_area = min_box_area;
+        self
+    }
+
+    pub fn with_window(mut self, window: usize) -> Self {
+        self.params.window = window;
+        self
+    }
+
+    pub fn params(&self) -> &MotionMog2Params {
+        &self.params
+    }
+
+    // the first frame only sets the size and type of the internal masks
+    pub fn build(self, prv_frame: &Mat) -> Result<MotionMog2> {
+        MotionMog2::from_params(prv_frame, self.params)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn params_round_trip_through_toml() {
+        let params = MotionMog2Builder::new()
+            .with_history(200)
+            .with_close([5, 3], 2, BorderType::Reflect101)
+            .with_contours(ContourMode::CComp, ContourApproximation::Tc89L1)
+            .params()
+            .clone();
+
+        let text = toml::to_string(&params).unwrap();
+        assert!(text.contains("close_border = \"reflect101\""), "{}", text);
+        assert!(text.contains("contour_mode = \"ccomp\""), "{}", text);
+        assert_eq!(toml::from_str::<MotionMog2Params>(&text).unwrap(), params);
+    }
+
+    #[test]
+    fn builder_rejects_invalid_params() {
+        let even_block = MotionMog2Builder::new().with_adaptive_threshold(16, 9.0);
+        let error = even_block.build(&Mat::default()).err().expect("even block size accepted");
+        assert!(error.to_string().contains("adaptive_block_size"), "{}", error);
+
+        let empty_kernel = MotionMog2Builder::new().with_erode([0, 2], 1, BorderType::Constant);
+        let error = empty_kernel.build(&Mat::default()).err().expect("empty kernel accepted");
+        assert!(error.to_string().contains("erode_kernel"), "{}", error);
+
+        let white_shadows = MotionMog2Builder::new().with_shadows(255, 0.5);
+        let error = white_shadows.build(&Mat::default()).err().expect("shadows marked as foreground accepted");
+        assert!(error.to_string().contains("shadow_value"), "{}", error);
+    }
+}
@@ -0,0 +1,69 @@
+use std::fmt;
+
+/// Everything that can go wrong while reading, decoding or analysing video.
+#[derive(Debug)]
+pub enum VisionError {
+    /// A file, device, stream or image sequence could not be opened.
+    SourceOpen { name: String, reason: String },
+    /// An output file could not be created or written.
+    SinkOpen { name: String, reason: String },
+    /// A source was opened but did not produce a usable frame.
+    Decode(String),
+    /// The configuration is inconsistent or out of range.
+    InvalidConfig(String),
+    /// A detector stage failed while processing a frame.
+    Detector { detector: &'static str, source: opencv::Error },
+    /// Any other OpenCV failure.
+    OpenCv(opencv::Error),
+}
+
+pub type Result<T> = std::result::Result<T, VisionError>;
+
+impl VisionError {
+    pub fn source_open(name: impl Into<String>, reason: impl fmt::Display) -> Self {
+        VisionError::SourceOpen {
+            name: name.into(),
+            reason: reason.to_string(),
+        }
+    }
+
+    pub fn sink_open(name: impl Into<String>, reason: impl fmt::Display) -> Self {
+        VisionError::SinkOpen {
+            name: name.into(),
+            reason: reason.to_string(),
+        }
+    }
+
+    pub fn detector(detector: &'static str) -> impl FnOnce(opencv::Error) -> Self {
+        move |source| VisionError::Detector { detector, source }
+    }
+}
+
+impl fmt::Display for VisionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VisionError::SourceOpen { name, reason } => write!(f, "unable to open {}: {}", name, reason),
+            VisionError::SinkOpen { name, reason } => write!(f, "unable to write {}: {}", name, reason),
+            VisionError::Decode(message) => write!(f, "unable to decode frame: {}", message),
+            VisionError::InvalidConfig(message) => write!(f, "invalid configuration: {}", message),
+            VisionError::Detector { detector, source } => write!(f, "{} detector failed: {}", detector, source),
+            VisionError::OpenCv(source) => write!(f, "OpenCV error: {}", source),
+        }
+    }
+}
+
+impl std::error::Error for VisionError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            VisionError::Detector { source, .. } => Some(source),
+            VisionError::OpenCv(source) => Some(source),
+            _ => None,
+        }
+    }
+}
+
+impl From<opencv::Error> for VisionError {
+    fn from(error: opencv::Error) -> Self {
+        VisionError::OpenCv(error)
+    }
+}
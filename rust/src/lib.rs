@@ -0,0 +1,17 @@
+//! OpenCV-based motion detection.
+//!
+//! Frames are read from a [`FrameSource`](sources::frame_source::FrameSource) into
+//! [`VideoFrames`](util::video_frames::VideoFrames), which keeps full, half and quarter resolution
+//! colour and monochrome copies of the current and previous frame. Detectors implementing
+//! [`Detector`](detectors::detector::Detector) are then run as a cascade, cheapest first, and
+//! [`VideoProcessor`](util::video_processor::VideoProcessor) drives the whole loop.
+
+pub mod config;
+pub mod detectors;
+pub mod error;
+pub mod masks;
+pub mod roi;
+pub mod sinks;
+pub mod sources;
+pub mod tracking;
+pub mod util;
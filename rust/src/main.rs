@@ -1,16 +1,44 @@
-mod detectors;
-mod util;
-mod masks;
-
 use std::path::Path;
+use std::process::exit;
 use structopt::StructOpt;
-use crate::util::video_processor::{ VideoProcessor, VideoConfig };
+use rusty_vision::config::{ Settings, VideoConfig };
+use rusty_vision::error::Result;
+use rusty_vision::util::video_processor::VideoProcessor;
 
 fn main() {
 
-    let mut video_proc = VideoProcessor::new();
     let conf = VideoConfig::from_args();
-    let path = Path::new(conf.input.to_str().unwrap());
-    video_proc.load_videos(path, &conf).unwrap();
+    if let Err(e) = run(&conf) {
+        eprintln!("Error: {}", e);
+        exit(1);
+    }
+
+}
+
+fn run(conf: &VideoConfig) -> Result<()> {
+
+    let settings = Settings::load(conf)?;
+    let silent = settings.output.silent;
+
+    let mut video_proc = VideoProcessor::new(settings)?;
+    if !silent {
+        video_proc.set_status_callback(Box::new(|status| println!("Stream {}", status)));
+    }
+
+    let result = if let Some(url) = &conf.stream {
+        video_proc.process_stream(url)
+    } else if let Some(images) = &conf.images {
+        video_proc.process_images(images)
+    } else if let Some(device) = &conf.device {
+        video_proc.process_device(device)
+    } else if let Some(input) = &conf.input {
+        video_proc.load_videos(Path::new(input))
+    } else {
+        Ok(())
+    };
+
+    // the output video is finalised even when processing failed part way through
+    let finished = video_proc.finish();
+    result.and(finished)
 
 }
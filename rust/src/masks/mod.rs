@@ -1,5 +1,10 @@
-#[allow(dead_code)]
+//! Overlays that visualise detector output on top of video frames, and the zone masks that
+//! restrict where detectors look.
+
 pub mod motion_overlay;
 
-#[allow(dead_code)]
-pub mod overlay;
\ No newline at end of file
+pub mod overlay;
+
+pub mod tripwire_overlay;
+
+pub mod zones;
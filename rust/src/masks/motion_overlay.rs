@@ -1,37 +1,75 @@
-use opencv::core::{add_weighted, Mat, MatExprTraitConst, MatTraitConst, Rect, Scalar, CV_8UC3};
+use opencv::core::{add_weighted, Mat, MatExprTraitConst, MatTraitConst, Point, Rect, Scalar, CV_8UC3};
 use opencv::Error;
-use opencv::imgproc::{resize, INTER_LINEAR};
-use crate::detectors::motion_mog2::MotionMog2;
+use opencv::imgproc::{put_text, resize, FONT_HERSHEY_SIMPLEX, INTER_LINEAR, LINE_8};
+use crate::config::{color, OverlaySettings};
 use crate::masks::overlay::OverlayProcessor;
+use crate::roi::classifier::Classification;
+use crate::tracking::tracker::Tracker;
 use opencv::prelude::MatTrait;
 
 const MAX_BOUNDING_BOXES: usize = 10;
 
+/// Tints a motion mask over a half resolution frame and outlines the bounding boxes found at
+/// the mask's resolution, labelled with their track IDs and classes when a tracker or
+/// classifications are given. Cast shadows are tinted in a colour of their own.
 pub struct MotionOverlay<'a> {
-    mog2_detector: &'a MotionMog2,
+    mask: &'a Mat,
+    boxes: &'a [Rect],
+    shadows: Option<&'a Mat>,
+    tracker: Option<&'a Tracker>,
+    classifications: Option<&'a [Option<Classification>]>,
     tint_color: Scalar,
+    tint_alpha: f64,
+    shadow_color: Scalar,
+    box_color: Scalar,
+    box_thickness: i32,
     bounding_boxes: Vec<Rect>,
 }
 
 impl<'a> MotionOverlay<'a> {
-    pub fn new(motion_mog2: &'a MotionMog2) -> Self {
+    pub fn new(mask: &'a Mat, boxes: &'a [Rect]) -> Self {
+        Self::with_settings(mask, boxes, &OverlaySettings::default())
+    }
+
+    pub fn with_settings(mask: &'a Mat, boxes: &'a [Rect], settings: &OverlaySettings) -> Self {
         Self {
-            mog2_detector: &motion_mog2,
-            tint_color: Scalar::new(
-                0.0,
-                0.0,
-                255.0,
-                0.0),
+            mask,
+            boxes,
+            shadows: None,
+            tracker: None,
+            classifications: None,
+            tint_color: color(settings.tint_color),
+            tint_alpha: settings.tint_alpha,
+            shadow_color: color(settings.shadow_color),
+            box_color: color(settings.box_color),
+            box_thickness: settings.box_thickness,
             bounding_boxes: Vec::with_capacity(MAX_BOUNDING_BOXES)
         }
     }
 
+    // a mask of the shadows left out of the motion mask, at the same resolution
+    pub fn with_shadows(mut self, shadows: &'a Mat) -> Self {
+        self.shadows = Some(shadows);
+        self
+    }
+
+    pub fn with_tracker(mut self, tracker: &'a Tracker) -> Self {
+        self.tracker = Some(tracker);
+        self
+    }
+
+    // one per bounding box, in the same order
+    pub fn with_classifications(mut self, classifications: &'a [Option<Classification>]) -> Self {
+        self.classifications = Some(classifications);
+        self
+    }
+
     pub fn get_bounding_boxes(&self) -> &Vec<Rect> {
         &self.bounding_boxes
     }
 }
 
-fn tint_mask(mask: &Mat, color: &Scalar) -> Result<Mat, Error> {
+fn tint_mask(mask: &Mat, color: &Scalar, shadows: Option<(&Mat, &Scalar)>) -> Result<Mat, Error> {
 
     let mut tinted_mask = Mat::zeros(
         mask.rows(),
@@ -39,6 +77,14 @@ fn tint_mask(mask: &Mat, color: &Scalar) -> Result<Mat, Error> {
         CV_8UC3
     )?.to_mat()?;
 
+    // motion is tinted over the shadows where the two meet
+    if let Some((shadows, shadow_color)) = shadows {
+        tinted_mask.set_to(
+            &shadow_color,
+            &shadows
+        )?;
+    }
+
     tinted_mask.set_to(
         &color,
         &mask
@@ -51,7 +97,13 @@ impl<'a> OverlayProcessor<'a> for MotionOverlay<'a> {
     fn draw(&mut self, frame: &Mat) -> Result<Mat, Error> {
 
         let mut overlay = frame.clone();
-        let mut tinted_mask = tint_mask(&self.mog2_detector.get_diff_mask(), &self.tint_color)?;
+        let mask = self.mask;
+        let shadows = self.shadows.map(|shadows| (shadows, &self.shadow_color));
+        let mut tinted_mask = tint_mask(mask, &self.tint_color, shadows)?;
+
+        // boxes are found at the detector's resolution, which need not be half of the frame's
+        let scale_x = frame.cols() as f64 / mask.cols().max(1) as f64;
+        let scale_y = frame.rows() as f64 / mask.rows().max(1) as f64;
 
         resize(
             &tinted_mask,
@@ -66,32 +118,105 @@ impl<'a> OverlayProcessor<'a> for MotionOverlay<'a> {
             &frame,
             1.0,
             &overlay,
-            0.5,
+            self.tint_alpha,
             0.0,
             &mut tinted_mask,
             -1,
         )?;
 
-        for current_rect in self.mog2_detector.get_bounding_boxes().iter() {
+        for (index, current_rect) in self.boxes.iter().enumerate() {
             if !self.bounding_boxes.iter().any(|outer_rect: &Rect| {
                 outer_rect.contains(current_rect.tl()) && outer_rect.contains(current_rect.br())
             }) {
+                let rect = Rect::new(
+                    (current_rect.x as f64 * scale_x).round() as i32,
+                    (current_rect.y as f64 * scale_y).round() as i32,
+                    (current_rect.width as f64 * scale_x).round() as i32,
+                    (current_rect.height as f64 * scale_y).round() as i32,
+                );
                 opencv::imgproc::rectangle(
                     &mut tinted_mask,
-                    Rect::new(
-                        current_rect.x * 2,
-                        current_rect.y * 2,
-                        current_rect.width * 2,
-                        current_rect.height * 2,
-                    ),
-                    Scalar::new(0.0, 255.0, 0.0, 0.0),
-                    2,
+                    rect,
+                    self.box_color,
+                    self.box_thickness,
                     opencv::imgproc::LINE_8,
                     0,
                 )?;
+
+                // the label sits above the box, or inside it at the top of the frame
+                let track = self.tracker
+                    .and_then(|tracker| tracker.track_of(current_rect))
+                    .map(|track| format!("#{}", track.id()));
+                let class = self.classifications
+                    .and_then(|classifications| classifications.get(index))
+                    .and_then(|classification| classification.as_ref())
+                    .map(|classification| format!("{} {:.0}%", classification.label, classification.confidence * 100.0));
+                let label: Vec<String> = track.into_iter().chain(class).collect();
+                if !label.is_empty() {
+                    put_text(
+                        &mut tinted_mask,
+                        &label.join(" "),
+                        Point::new(rect.x, (rect.y - 4).max(12)),
+                        FONT_HERSHEY_SIMPLEX,
+                        0.5,
+                        self.box_color,
+                        1,
+                        LINE_8,
+                        false,
+                    )?;
+                }
             }
         }
 
         Ok(tinted_mask.clone())
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use opencv::core::{self, Size, Vec3b};
+    use crate::detectors::motion_mog2::MotionMog2;
+    use crate::sources::synthetic_scene::{Shape, SyntheticScene};
+    use crate::util::video_frames::{FrameProcessor, VideoFrames};
+
+    #[test]
+    fn draws_detected_boxes_at_half_resolution() {
+        let mut scene = SyntheticScene::fixture(Some(Shape::Rectangle));
+        let mut video_frames = VideoFrames::new(
+            Size::new(1280, 720),
+            Size::new(640, 360),
+            Size::new(320, 180),
+        ).unwrap();
+        assert!(video_frames.read_frame(&mut scene).unwrap());
+        let mut mog2 = MotionMog2::new(&video_frames.mono.quarter.cur, 500, 50.0, 17, 9.0).unwrap();
+        while video_frames.read_frame(&mut scene).unwrap() {
+            mog2.update(&video_frames).unwrap();
+        }
+
+        let frame = &video_frames.color.half.cur;
+        let overlay = MotionOverlay::new(mog2.get_diff_mask(), mog2.get_bounding_boxes()).draw(frame).unwrap();
+        assert_eq!(overlay.size().unwrap(), frame.size().unwrap());
+
+        let rect = mog2.get_bounding_boxes().first().copied().expect("no motion detected");
+        let corner = overlay.at_2d::<Vec3b>(rect.y * 2, rect.x * 2).unwrap();
+        assert_eq!(*corner, Vec3b::from([0, 255, 0]));
+    }
+
+    #[test]
+    fn tints_shadows_in_their_own_color() {
+        // motion on the left half, shadows on the bottom half, a black frame fully tinted
+        let settings = OverlaySettings { tint_alpha: 1.0, ..OverlaySettings::default() };
+        let mut mask = Mat::new_size_with_default(Size::new(8, 8), core::CV_8UC1, Scalar::all(0.0)).unwrap();
+        opencv::imgproc::rectangle(&mut mask, Rect::new(0, 0, 4, 8), Scalar::all(255.0), opencv::imgproc::FILLED, LINE_8, 0).unwrap();
+        let mut shadows = Mat::new_size_with_default(Size::new(8, 8), core::CV_8UC1, Scalar::all(0.0)).unwrap();
+        opencv::imgproc::rectangle(&mut shadows, Rect::new(0, 4, 8, 4), Scalar::all(255.0), opencv::imgproc::FILLED, LINE_8, 0).unwrap();
+        let frame = Mat::new_size_with_default(Size::new(8, 8), CV_8UC3, Scalar::all(0.0)).unwrap();
+
+        let overlay = MotionOverlay::with_settings(&mask, &[], &settings).with_shadows(&shadows).draw(&frame).unwrap();
+        let pixel = |row, col| overlay.at_2d::<Vec3b>(row, col).unwrap().0;
+        assert_eq!(pixel(6, 6), settings.shadow_color.map(|c| c as u8));
+        assert_eq!(pixel(6, 1), settings.tint_color.map(|c| c as u8));
+        assert_eq!(pixel(1, 6), [0, 0, 0]);
+    }
+}
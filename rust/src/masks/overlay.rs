@@ -1,6 +1,7 @@
 use opencv::core::Mat;
 use opencv::Error;
 
+/// Draws on top of a copy of the given frame and returns the result.
 pub trait OverlayProcessor <'a> {
     fn draw(
         &mut self,
@@ -0,0 +1,86 @@
+use opencv::core::{Mat, MatTraitConst, Point, Point2d, Scalar, Size};
+use opencv::Error;
+use opencv::imgproc::{line, put_text, FONT_HERSHEY_SIMPLEX, LINE_8};
+use crate::config::{color, OverlaySettings};
+use crate::masks::overlay::OverlayProcessor;
+use crate::tracking::tripwire::Tripwires;
+
+/// Draws the tripwires with their A>B and B>A counts over a frame, whether there is motion in
+/// it or not. The lines are kept at the detectors' resolution and scaled to the frame's.
+pub struct TripwireOverlay<'a> {
+    tripwires: &'a Tripwires,
+    detector_size: Size,
+    color: Scalar,
+    thickness: i32,
+}
+
+impl<'a> TripwireOverlay<'a> {
+    pub fn new(tripwires: &'a Tripwires, detector_size: Size) -> Self {
+        Self::with_settings(tripwires, detector_size, &OverlaySettings::default())
+    }
+
+    pub fn with_settings(tripwires: &'a Tripwires, detector_size: Size, settings: &OverlaySettings) -> Self {
+        Self {
+            tripwires,
+            detector_size,
+            color: color(settings.tripwire_color),
+            thickness: settings.box_thickness,
+        }
+    }
+}
+
+impl<'a> OverlayProcessor<'a> for TripwireOverlay<'a> {
+    fn draw(&mut self, frame: &Mat) -> Result<Mat, Error> {
+
+        let mut overlay = frame.clone();
+        let scale_x = frame.cols() as f64 / self.detector_size.width.max(1) as f64;
+        let scale_y = frame.rows() as f64 / self.detector_size.height.max(1) as f64;
+        let scale = |point: Point2d| Point::new((point.x * scale_x).round() as i32, (point.y * scale_y).round() as i32);
+
+        for tripwire in self.tripwires.lines() {
+            let (start, end) = (scale(tripwire.start()), scale(tripwire.end()));
+            line(&mut overlay, start, end, self.color, self.thickness, LINE_8, 0)?;
+            put_text(
+                &mut overlay,
+                &format!("{}  A>B {}  B>A {}", tripwire.name(), tripwire.a_to_b(), tripwire.b_to_a()),
+                Point::new(start.x, (start.y - 6).max(12)),
+                FONT_HERSHEY_SIMPLEX,
+                0.5,
+                self.color,
+                1,
+                LINE_8,
+                false,
+            )?;
+        }
+
+        Ok(overlay)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use opencv::core::{self, Vec3b};
+    use crate::config::TripwireSettings;
+
+    #[test]
+    fn draws_tripwires_without_motion() {
+        // a horizontal line across the middle of a 1280x720 frame, drawn on a still half resolution frame
+        let tripwires = Tripwires::new(
+            &[TripwireSettings {
+                name: String::from("door"),
+                start: [200.0, 360.0],
+                end: [1080.0, 360.0],
+            }],
+            Size::new(1280, 720),
+            Size::new(320, 180),
+        );
+        let frame = Mat::new_size_with_default(Size::new(640, 360), core::CV_8UC3, Scalar::all(0.0)).unwrap();
+
+        let overlay = TripwireOverlay::new(&tripwires, Size::new(320, 180)).draw(&frame).unwrap();
+        let tripwire_color = OverlaySettings::default().tripwire_color.map(|c| c as u8);
+        assert_eq!(overlay.at_2d::<Vec3b>(180, 320).unwrap().0, tripwire_color);
+        assert_eq!(overlay.at_2d::<Vec3b>(300, 320).unwrap().0, [0, 0, 0]);
+        assert_eq!(frame.at_2d::<Vec3b>(180, 320).unwrap().0, [0, 0, 0]);
+    }
+}
@@ -0,0 +1,319 @@
+use opencv::core::{self, Mat, MatTraitConst, Point, Point2d, Scalar, Size, Vector};
+use opencv::imgproc;
+use crate::config::{MotionEventSettings, ZoneKind, ZoneSettings};
+use crate::detectors::motion_events::{MotionEvent, MotionEvents};
+use crate::error::Result;
+use crate::util::video_frames::{Tier, VideoFrames};
+
+/// A mask at each resolution of `FrameSamples`, 255 where it applies and 0 elsewhere.
+pub struct ZoneMask {
+    pub full: Mat,
+    pub half: Mat,
+    pub quarter: Mat,
+}
+
+impl ZoneMask {
+    pub fn tier(&self, tier: Tier) -> &Mat {
+        match tier {
+            Tier::Full => &self.full,
+            Tier::Half => &self.half,
+            Tier::Quarter => &self.quarter,
+        }
+    }
+
+    // every pixel set to the value
+    fn filled(video_frames: &VideoFrames, value: f64) -> Result<Self> {
+        let mask = |size| Mat::new_size_with_default(size, core::CV_8UC1, Scalar::all(value));
+        Ok(Self {
+            full: mask(video_frames.size_full())?,
+            half: mask(video_frames.size_half())?,
+            quarter: mask(video_frames.size_quarter())?,
+        })
+    }
+
+    // fills a polygon given in full resolution pixels at every resolution
+    fn fill(&mut self, points: &[[f64; 2]], full_size: Size, value: f64) -> Result<()> {
+        for mask in [&mut self.full, &mut self.half, &mut self.quarter] {
+            let polygon: Vector<Point> = scale(points, full_size, mask.size()?).into_iter().collect();
+            imgproc::fill_poly(
+                mask,
+                &Vector::<Vector<Point>>::from_iter([polygon]),
+                Scalar::all(value),
+                imgproc::LINE_8,
+                0,
+                Point::default(),
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// A change of the debounced motion state inside one zone.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ZoneEvent {
+    pub zone: String,
+    pub kind: ZoneKind,
+    pub area: Option<f64>,              // moving pixels in the zone, None when MOG2 did not run
+    pub event: MotionEvent,
+}
+
+/// A configured polygon with its masks and the motion measured inside it.
+pub struct Zone {
+    name: String,
+    kind: ZoneKind,
+    mask: ZoneMask,
+    area: Option<f64>,
+    loiter_ms: u64,
+    motion_events: MotionEvents,
+}
+
+impl Zone {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn kind(&self) -> ZoneKind {
+        self.kind
+    }
+
+    pub fn mask(&self) -> &ZoneMask {
+        &self.mask
+    }
+
+    // moving pixels inside the zone in the latest update, at quarter resolution
+    pub fn area(&self) -> Option<f64> {
+        self.area
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.motion_events.is_active()
+    }
+
+    pub fn loiter_ms(&self) -> u64 {
+        self.loiter_ms
+    }
+
+    // whether a point at quarter resolution lies inside the zone
+    pub fn contains(&self, point: Point2d) -> Result<bool> {
+        let (row, col) = (point.y.round() as i32, point.x.round() as i32);
+        let mask = &self.mask.quarter;
+        if row < 0 || col < 0 || row >= mask.rows() || col >= mask.cols() {
+            return Ok(false);
+        }
+        Ok(*mask.at_2d::<u8>(row, col)? > 0)
+    }
+}
+
+/// The include and exclude zones of a source, rasterised at its resolutions. Their combined
+/// detection mask keeps the detectors to the areas of interest, and each zone debounces the
+/// motion inside it into events of its own.
+pub struct Zones {
+    zones: Vec<Zone>,
+    detection_mask: Option<ZoneMask>,   // None without any zones
+    overlap: Mat,                       // foreground inside the zone being measured
+}
+
+impl Zones {
+    // the zones debounce like `motion`, but stop at thresholds of their own: the global one is
+    // measured against the score of the last cascade stage rather than pixels in a zone
+    pub fn new(settings: &[ZoneSettings], motion: &MotionEventSettings, video_frames: &VideoFrames) -> Result<Self> {
+        let full_size = video_frames.size_full();
+        let mut zones = Vec::with_capacity(settings.len());
+        for zone in settings {
+            let mut mask = ZoneMask::filled(video_frames, 0.0)?;
+            mask.fill(&zone.points, full_size, 255.0)?;
+            zones.push(Zone {
+                name: zone.name.clone(),
+                kind: zone.kind,
+                mask,
+                area: None,
+                loiter_ms: zone.loiter_ms,
+                motion_events: MotionEvents::new(zone.threshold, &MotionEventSettings {
+                    stop_threshold: zone.stop_threshold.unwrap_or(zone.threshold),
+                    ..motion.clone()
+                }),
+            });
+        }
+
+        let detection_mask = if settings.is_empty() {
+            None
+        } else {
+            // include zones first, so that an exclude zone inside one still cuts it out
+            let includes = settings.iter().any(|zone| zone.kind == ZoneKind::Include);
+            let mut mask = ZoneMask::filled(video_frames, if includes { 0.0 } else { 255.0 })?;
+            for zone in settings.iter().filter(|zone| zone.kind == ZoneKind::Include) {
+                mask.fill(&zone.points, full_size, 255.0)?;
+            }
+            for zone in settings.iter().filter(|zone| zone.kind == ZoneKind::Exclude) {
+                mask.fill(&zone.points, full_size, 0.0)?;
+            }
+            Some(mask)
+        };
+
+        Ok(Self {
+            zones,
+            detection_mask,
+            overlap: Mat::default(),
+        })
+    }
+
+    pub fn zones(&self) -> &Vec<Zone> {
+        &self.zones
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.zones.is_empty()
+    }
+
+    // where the detectors look: inside any include zone and outside every exclude zone
+    pub fn detection_mask(&self) -> Option<&ZoneMask> {
+        self.detection_mask.as_ref()
+    }
+
+    // the foreground is the binary quarter resolution motion mask, None when MOG2 did not run
+    pub fn update(&mut self, foreground: Option<&Mat>, frame: u64, timestamp_ms: f64) -> Result<Vec<ZoneEvent>> {
+        let mut events = Vec::new();
+        for zone in self.zones.iter_mut() {
+            zone.area = match foreground {
+                Some(foreground) => {
+                    core::bitwise_and(foreground, &zone.mask.quarter, &mut self.overlap, &core::no_array())?;
+                    Some(core::count_non_zero(&self.overlap)? as f64)
+                }
+                None => None,
+            };
+            if let Some(event) = zone.motion_events.update(frame, timestamp_ms, zone.area) {
+                events.push(ZoneEvent {
+                    zone: zone.name.clone(),
+                    kind: zone.kind,
+                    area: zone.area,
+                    event,
+                });
+            }
+        }
+        Ok(events)
+    }
+
+    // closes the events still going when the source ends
+    pub fn finish(&mut self, frame: u64, timestamp_ms: f64) -> Vec<ZoneEvent> {
+        self.zones.iter_mut()
+            .filter_map(|zone| {
+                zone.motion_events.finish(frame, timestamp_ms).map(|event| ZoneEvent {
+                    zone: zone.name.clone(),
+                    kind: zone.kind,
+                    area: None,
+                    event,
+                })
+            })
+            .collect()
+    }
+}
+
+// maps polygon corners from one resolution onto another, rounded to whole pixels
+pub fn scale(points: &[[f64; 2]], from: Size, to: Size) -> Vec<Point> {
+    let scale_x = to.width as f64 / from.width.max(1) as f64;
+    let scale_y = to.height as f64 / from.height.max(1) as f64;
+    points.iter()
+        .map(|[x, y]| Point::new((x * scale_x).round() as i32, (y * scale_y).round() as i32))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use opencv::core::{MatExprTraitConst, Rect};
+
+    fn video_frames() -> VideoFrames {
+        VideoFrames::new(Size::new(1280, 720), Size::new(640, 360), Size::new(320, 180)).unwrap()
+    }
+
+    fn zone(name: &str, kind: ZoneKind, [x, y, width, height]: [f64; 4]) -> ZoneSettings {
+        ZoneSettings {
+            name: name.to_string(),
+            kind,
+            points: vec![[x, y], [x + width, y], [x + width, y + height], [x, y + height]],
+            threshold: 100.0,
+            ..ZoneSettings::default()
+        }
+    }
+
+    #[test]
+    fn polygons_are_scaled_to_each_resolution() {
+        let points = [[200.0, 360.0], [1080.0, 360.0], [640.0, 719.0]];
+        assert_eq!(
+            scale(&points, Size::new(1280, 720), Size::new(320, 180)),
+            vec![Point::new(50, 90), Point::new(270, 90), Point::new(160, 180)],
+        );
+    }
+
+    #[test]
+    fn detection_mask_keeps_includes_minus_excludes() {
+        // the left half is of interest, except for a square in its middle
+        let settings = vec![
+            zone("left", ZoneKind::Include, [0.0, 0.0, 640.0, 720.0]),
+            zone("tv", ZoneKind::Exclude, [160.0, 200.0, 160.0, 160.0]),
+        ];
+        let zones = Zones::new(&settings, &MotionEventSettings::default(), &video_frames()).unwrap();
+        let mask = zones.detection_mask().unwrap();
+
+        assert_eq!(*mask.full.at_2d::<u8>(100, 100).unwrap(), 255);
+        assert_eq!(*mask.full.at_2d::<u8>(280, 240).unwrap(), 0);
+        assert_eq!(*mask.full.at_2d::<u8>(100, 1000).unwrap(), 0);
+        assert_eq!(*mask.half.at_2d::<u8>(50, 50).unwrap(), 255);
+        assert_eq!(*mask.quarter.at_2d::<u8>(70, 60).unwrap(), 0);
+        assert_eq!(*mask.quarter.at_2d::<u8>(25, 250).unwrap(), 0);
+
+        // without include zones the whole frame is of interest
+        let zones = Zones::new(&settings[1..], &MotionEventSettings::default(), &video_frames()).unwrap();
+        assert_eq!(*zones.detection_mask().unwrap().quarter.at_2d::<u8>(25, 250).unwrap(), 255);
+        assert!(Zones::new(&[], &MotionEventSettings::default(), &video_frames()).unwrap().detection_mask().is_none());
+    }
+
+    #[test]
+    fn each_zone_measures_its_own_motion() {
+        let settings = vec![
+            zone("left", ZoneKind::Include, [0.0, 0.0, 640.0, 720.0]),
+            zone("right", ZoneKind::Include, [640.0, 0.0, 640.0, 720.0]),
+        ];
+        let mut zones = Zones::new(&settings, &MotionEventSettings::default(), &video_frames()).unwrap();
+
+        // a 20x20 blob well inside the left half at quarter resolution
+        let mut foreground = Mat::zeros(180, 320, core::CV_8UC1).unwrap().to_mat().unwrap();
+        imgproc::rectangle(&mut foreground, Rect::new(40, 40, 20, 20), Scalar::all(255.0), imgproc::FILLED, imgproc::LINE_8, 0).unwrap();
+
+        let events = zones.update(Some(&foreground), 1, 100.0).unwrap();
+        assert_eq!(zones.zones()[0].area(), Some(400.0));
+        assert_eq!(zones.zones()[1].area(), Some(0.0));
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].zone, "left");
+        assert!(matches!(events[0].event, MotionEvent::MotionStarted { frame: 1, .. }));
+
+        let events = zones.update(None, 2, 200.0).unwrap();
+        assert_eq!(zones.zones()[0].area(), None);
+        assert!(matches!(events[0].event, MotionEvent::MotionEnded { duration_ms, .. } if duration_ms == 100.0));
+        assert!(zones.finish(3, 300.0).is_empty());
+    }
+
+    #[test]
+    fn zones_stop_at_their_own_threshold() {
+        let mut settings = vec![zone("left", ZoneKind::Include, [0.0, 0.0, 640.0, 720.0])];
+        let mut foreground = Mat::zeros(180, 320, core::CV_8UC1).unwrap().to_mat().unwrap();
+        imgproc::rectangle(&mut foreground, Rect::new(40, 40, 20, 20), Scalar::all(255.0), imgproc::FILLED, imgproc::LINE_8, 0).unwrap();
+        let mut shrunk = Mat::zeros(180, 320, core::CV_8UC1).unwrap().to_mat().unwrap();
+        imgproc::rectangle(&mut shrunk, Rect::new(40, 40, 5, 10), Scalar::all(255.0), imgproc::FILLED, imgproc::LINE_8, 0).unwrap();
+
+        // the global stop threshold is a cascade score and leaves the zone alone
+        let events = |settings: &[ZoneSettings]| {
+            let motion = MotionEventSettings { stop_threshold: 8000.0, ..MotionEventSettings::default() };
+            let mut zones = Zones::new(settings, &motion, &video_frames()).unwrap();
+            [&foreground, &foreground, &shrunk].into_iter()
+                .enumerate()
+                .map(|(index, foreground)| zones.update(Some(foreground), index as u64, index as f64 * 100.0).unwrap().len())
+                .collect::<Vec<usize>>()
+        };
+        assert_eq!(events(&settings), vec![1, 0, 1]);
+
+        // 50 moving pixels keep the zone's motion going once it stops below 40
+        settings[0].stop_threshold = Some(40.0);
+        assert_eq!(events(&settings), vec![1, 0, 0]);
+    }
+}
@@ -0,0 +1,151 @@
+use std::fs;
+use opencv::core::{self, Mat, MatTraitConst, Point, Rect, Scalar};
+use opencv::dnn::{self, Net, NetTrait};
+use serde::{Deserialize, Serialize};
+use crate::config::{color, size, ClassifierSettings};
+use crate::error::{Result, VisionError};
+use crate::roi::extractor::RoiExtractor;
+use crate::util::video_frames::VideoFrames;
+
+/// The most likely class of a region.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Classification {
+    pub label: String,
+    pub confidence: f64,
+}
+
+/// An ONNX image classifier run on the CPU with OpenCV's DNN module. It only ever sees the
+/// regions of interest cut out around the motion boxes, letterboxed to the model's input size.
+pub struct Classifier {
+    net: Net,
+    labels: Vec<String>,
+    extractor: RoiExtractor,
+    settings: ClassifierSettings,
+}
+
+impl Classifier {
+    pub fn new(settings: &ClassifierSettings) -> Result<Self> {
+        let mut net = dnn::read_net_from_onnx(&settings.model)
+            .map_err(|e| VisionError::source_open(&settings.model, e))?;
+        net.set_preferable_backend(dnn::DNN_BACKEND_OPENCV)?;
+        net.set_preferable_target(dnn::DNN_TARGET_CPU)?;
+
+        let labels = if settings.labels.is_empty() {
+            Vec::new()
+        } else {
+            load_labels(&settings.labels)?
+        };
+
+        // letterbox bars in the mean colour come out of the blob as zeros
+        let [first, second, third] = settings.mean;
+        let fill = if settings.swap_rb { Scalar::new(third, second, first, 0.0) } else { color(settings.mean) };
+
+        Ok(Self {
+            net,
+            labels,
+            extractor: RoiExtractor::new()
+                .with_padding(settings.padding)
+                .with_input_size(size(settings.input_size), fill),
+            settings: settings.clone(),
+        })
+    }
+
+    pub fn labels(&self) -> &Vec<String> {
+        &self.labels
+    }
+
+    // None when no class reaches the minimum confidence
+    pub fn classify(&mut self, image: &Mat) -> Result<Option<Classification>> {
+        let scores = self.forward(image).map_err(VisionError::detector("Classifier"))?;
+        Ok(best(&scores, self.settings.softmax)
+            .filter(|(_, confidence)| *confidence >= self.settings.min_confidence)
+            .map(|(index, confidence)| Classification {
+                label: self.labels.get(index).cloned().unwrap_or_else(|| index.to_string()),
+                confidence,
+            }))
+    }
+
+    // one result per quarter resolution box, that of the region the box ended up in
+    pub fn classify_boxes(&mut self, boxes: &[Rect], video_frames: &VideoFrames) -> Result<Vec<Option<Classification>>> {
+        let rois = self.extractor.extract(boxes, video_frames)?;
+        let mut classifications = Vec::with_capacity(rois.len());
+        for roi in &rois {
+            classifications.push(self.classify(&roi.image)?);
+        }
+
+        Ok(boxes.iter()
+            .map(|rect| {
+                let rect = video_frames.quarter_to_full(*rect);
+                let centre = Point::new(rect.x + rect.width / 2, rect.y + rect.height / 2);
+                rois.iter()
+                    .position(|roi| roi.region.contains(centre))
+                    .and_then(|index| classifications[index].clone())
+            })
+            .collect())
+    }
+
+    fn forward(&mut self, image: &Mat) -> opencv::Result<Vec<f32>> {
+        let blob = dnn::blob_from_image(
+            image,
+            self.settings.scale,
+            size(self.settings.input_size),
+            color(self.settings.mean),
+            self.settings.swap_rb,
+            false,
+            core::CV_32F,
+        )?;
+        self.net.set_input(&blob, "", 1.0, Scalar::default())?;
+        let output = self.net.forward_single("")?;
+        Ok(output.data_typed::<f32>()?.to_vec())
+    }
+}
+
+// one class name per line, surrounding whitespace and trailing blank lines dropped
+pub fn load_labels(path: &str) -> Result<Vec<String>> {
+    let contents = fs::read_to_string(path).map_err(|e| VisionError::source_open(path, e))?;
+    let mut labels: Vec<String> = contents.lines().map(|line| line.trim().to_string()).collect();
+    while labels.last().is_some_and(|label| label.is_empty()) {
+        labels.pop();
+    }
+    Ok(labels)
+}
+
+// index and probability of the highest score, through a softmax if the scores are raw
+fn best(scores: &[f32], softmax: bool) -> Option<(usize, f64)> {
+    let (index, top) = scores.iter()
+        .enumerate()
+        .max_by(|a, b| a.1.total_cmp(b.1))?;
+    let confidence = if softmax {
+        1.0 / scores.iter().map(|score| (*score as f64 - *top as f64).exp()).sum::<f64>()
+    } else {
+        *top as f64
+    };
+    Some((index, confidence))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    #[test]
+    fn picks_the_most_likely_class() {
+        assert_eq!(best(&[0.1, 0.7, 0.2], false), Some((1, 0.7f32 as f64)));
+        assert_eq!(best(&[], false), None);
+
+        // equal raw scores share the probability
+        let (index, confidence) = best(&[2.0, 5.0, 5.0, 5.0, 5.0], true).unwrap();
+        assert!(index >= 1);
+        assert!((confidence - 0.2469).abs() < 0.001, "{}", confidence);
+    }
+
+    #[test]
+    fn labels_are_read_one_per_line() {
+        let path = env::temp_dir().join(format!("rusty_vision_labels_{}.txt", std::process::id()));
+        fs::write(&path, "person\n bicycle \r\n\ncar\n\n").unwrap();
+        let labels = load_labels(path.to_str().unwrap()).unwrap();
+        fs::remove_file(&path).ok();
+        assert_eq!(labels, vec!["person", "bicycle", "", "car"]);
+        assert!(load_labels("/nonexistent/labels.txt").is_err());
+    }
+}
@@ -0,0 +1,217 @@
+use opencv::core::{self, Mat, MatTraitConst, Point, Point2d, Rect, Scalar, Size};
+use opencv::imgproc;
+use crate::error::Result;
+use crate::util::video_frames::VideoFrames;
+
+/// A crop of the full resolution frame, with the region of the frame it was taken from.
+pub struct Roi {
+    pub region: Rect,                   // full resolution pixels, after padding and merging
+    pub image: Mat,                     // the crop, letterboxed when an input size is set
+    pub scale: f64,                     // image pixels per frame pixel, 1 unless letterboxed
+    pub offset: Point,                  // top left of the crop inside the image
+}
+
+impl Roi {
+    // maps a point in the image, e.g. from a model's output, back onto the full resolution frame
+    pub fn to_frame(&self, point: Point2d) -> Point2d {
+        Point2d::new(
+            self.region.x as f64 + (point.x - self.offset.x as f64) / self.scale,
+            self.region.y as f64 + (point.y - self.offset.y as f64) / self.scale,
+        )
+    }
+}
+
+/// Turns the quarter resolution MOG2 boxes into crops of the full resolution colour frame.
+/// Boxes are padded by a fraction of their size on every side and clipped to the frame, and
+/// regions that then overlap are merged into one, so that an object split into several blobs
+/// is seen whole. With an input size set, each crop is scaled to fit it and padded to exactly
+/// that size, keeping its aspect ratio.
+pub struct RoiExtractor {
+    padding: f64,
+    merge: bool,
+    input_size: Option<Size>,
+    fill: Scalar,                       // colour of the letterbox bars
+}
+
+impl RoiExtractor {
+    pub fn new() -> Self {
+        Self {
+            padding: 0.1,
+            merge: true,
+            input_size: None,
+            fill: Scalar::all(114.0),
+        }
+    }
+
+    pub fn with_padding(mut self, padding: f64) -> Self {
+        self.padding = padding;
+        self
+    }
+
+    pub fn with_merge(mut self, merge: bool) -> Self {
+        self.merge = merge;
+        self
+    }
+
+    pub fn with_input_size(mut self, input_size: Size, fill: Scalar) -> Self {
+        self.input_size = Some(input_size);
+        self.fill = fill;
+        self
+    }
+
+    // the full resolution regions for the boxes, top to bottom
+    pub fn regions(&self, boxes: &[Rect], video_frames: &VideoFrames) -> Vec<Rect> {
+        let frame = Rect::new(0, 0, video_frames.size_full().width, video_frames.size_full().height);
+        let mut regions: Vec<Rect> = boxes.iter()
+            .map(|rect| {
+                let rect = video_frames.quarter_to_full(*rect);
+                let dx = (rect.width as f64 * self.padding).round() as i32;
+                let dy = (rect.height as f64 * self.padding).round() as i32;
+                Rect::new(rect.x - dx, rect.y - dy, rect.width + 2 * dx, rect.height + 2 * dy) & frame
+            })
+            .filter(|rect| rect.area() > 0)
+            .collect();
+
+        if self.merge {
+            regions = merge(regions);
+        }
+        regions.sort_by_key(|rect| (rect.y, rect.x));
+        regions
+    }
+
+    pub fn extract(&self, boxes: &[Rect], video_frames: &VideoFrames) -> Result<Vec<Roi>> {
+        let frame = &video_frames.color.full.cur;
+        self.regions(boxes, video_frames).into_iter()
+            .map(|region| {
+                let crop = Mat::roi(frame, region)?.try_clone()?;
+                match self.input_size {
+                    Some(input_size) => self.letterbox(region, &crop, input_size),
+                    None => Ok(Roi {
+                        region,
+                        image: crop,
+                        scale: 1.0,
+                        offset: Point::new(0, 0),
+                    }),
+                }
+            })
+            .collect()
+    }
+
+    fn letterbox(&self, region: Rect, crop: &Mat, input_size: Size) -> Result<Roi> {
+        let scale = (input_size.width as f64 / crop.cols() as f64).min(input_size.height as f64 / crop.rows() as f64);
+        let width = ((crop.cols() as f64 * scale).round() as i32).clamp(1, input_size.width);
+        let height = ((crop.rows() as f64 * scale).round() as i32).clamp(1, input_size.height);
+
+        let mut resized = Mat::default();
+        imgproc::resize(crop, &mut resized, Size::new(width, height), 0.0, 0.0, imgproc::INTER_LINEAR)?;
+
+        // centred, with any odd pixel of padding going to the bottom and right
+        let left = (input_size.width - width) / 2;
+        let top = (input_size.height - height) / 2;
+        let mut image = Mat::default();
+        core::copy_make_border(
+            &resized,
+            &mut image,
+            top,
+            input_size.height - height - top,
+            left,
+            input_size.width - width - left,
+            core::BORDER_CONSTANT,
+            self.fill,
+        )?;
+
+        Ok(Roi {
+            region,
+            image,
+            scale,
+            offset: Point::new(left, top),
+        })
+    }
+}
+
+impl Default for RoiExtractor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// replaces overlapping regions by their union until none overlap
+fn merge(mut regions: Vec<Rect>) -> Vec<Rect> {
+    let mut merged = true;
+    while merged {
+        merged = false;
+        'search: for i in 0..regions.len() {
+            for j in i + 1..regions.len() {
+                if (regions[i] & regions[j]).area() > 0 {
+                    regions[i] = regions[i] | regions[j];
+                    regions.swap_remove(j);
+                    merged = true;
+                    break 'search;
+                }
+            }
+        }
+    }
+    regions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn video_frames() -> VideoFrames {
+        let mut video_frames = VideoFrames::new(Size::new(1280, 720), Size::new(640, 360), Size::new(320, 180)).unwrap();
+        let frame = Mat::new_size_with_default(Size::new(1280, 720), core::CV_8UC3, Scalar::all(200.0)).unwrap();
+        video_frames.load_frame(frame).unwrap();
+        video_frames
+    }
+
+    // two overlapping blobs, one on its own and one at the bottom edge, at quarter resolution
+    fn boxes() -> Vec<Rect> {
+        vec![
+            Rect::new(10, 10, 20, 20),
+            Rect::new(200, 100, 10, 10),
+            Rect::new(25, 15, 20, 20),
+            Rect::new(0, 170, 20, 10),
+        ]
+    }
+
+    #[test]
+    fn boxes_are_scaled_padded_clipped_and_merged() {
+        let video_frames = video_frames();
+        let extractor = RoiExtractor::new().with_padding(0.25);
+        assert_eq!(
+            extractor.regions(&boxes(), &video_frames),
+            vec![
+                Rect::new(20, 20, 180, 140),
+                Rect::new(790, 390, 60, 60),
+                Rect::new(0, 670, 100, 50),
+            ],
+        );
+        assert_eq!(extractor.with_merge(false).regions(&boxes(), &video_frames).len(), 4);
+    }
+
+    #[test]
+    fn crops_are_letterboxed_to_the_input_size() {
+        let video_frames = video_frames();
+        let rois = RoiExtractor::new()
+            .with_padding(0.25)
+            .with_input_size(Size::new(64, 64), Scalar::all(0.0))
+            .extract(&boxes(), &video_frames)
+            .unwrap();
+        assert_eq!(rois.len(), 3);
+
+        // 180x140 fits 64 wide, leaving bars of 7 pixels above and below
+        let roi = &rois[0];
+        assert_eq!(roi.image.size().unwrap(), Size::new(64, 64));
+        assert_eq!(roi.offset, Point::new(0, 7));
+        assert_eq!(roi.image.at_2d::<core::Vec3b>(0, 32).unwrap().0, [0, 0, 0]);
+        assert_eq!(roi.image.at_2d::<core::Vec3b>(32, 32).unwrap().0, [200, 200, 200]);
+
+        let centre = roi.to_frame(Point2d::new(32.0, 32.0));
+        assert!((centre.x - 110.0).abs() < 1.0 && (centre.y - 90.0).abs() < 1.0, "{:?}", centre);
+
+        let plain = RoiExtractor::new().with_padding(0.25).extract(&boxes(), &video_frames).unwrap();
+        assert_eq!(plain[1].image.size().unwrap(), Size::new(60, 60));
+        assert_eq!(plain[1].to_frame(Point2d::new(0.0, 0.0)), Point2d::new(790.0, 390.0));
+    }
+}
@@ -0,0 +1,8 @@
+//! Regions of interest: the parts of a frame that the motion cascade flagged, cropped at full
+//! resolution so that expensive models, local or behind an HTTP endpoint, only ever look at those.
+
+pub mod classifier;
+
+pub mod extractor;
+
+pub mod vlm;
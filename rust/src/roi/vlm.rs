@@ -0,0 +1,351 @@
+use std::io;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::time::{Duration, Instant};
+use opencv::core::{Mat, MatTraitConst, Rect, Size, Vector};
+use opencv::{imgcodecs, imgproc};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tokio::runtime::{self, Runtime};
+use crate::config::{VlmApi, VlmSettings};
+use crate::error::{Result, VisionError};
+use crate::roi::extractor::RoiExtractor;
+use crate::sinks::event_log::BoundingBox;
+use crate::util::http::{self, Url};
+use crate::util::video_frames::VideoFrames;
+
+/// What the vision-language model said about the regions of confirmed motion in one frame.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Description {
+    pub source: String,
+    pub frame: u64,                     // the frame the crops were taken from
+    pub timestamp_ms: f64,
+    pub regions: Vec<BoundingBox>,      // the crops, in full resolution pixels
+    pub description: String,
+    pub latency_ms: f64,                // from sending the request to the answer
+}
+
+// a request still waiting for its answer
+struct Pending {
+    source: String,
+    frame: u64,
+    timestamp_ms: f64,
+    regions: Vec<BoundingBox>,
+    sent: Instant,
+}
+
+/// Sends JPEG crops of the motion to an OpenAI or Ollama compatible endpoint and collects the
+/// descriptions it answers with. Requests run on a tokio runtime of their own so that the
+/// frame loop never waits for the model; it hands crops over with [`VlmClient::submit`] and
+/// picks up the answers with [`VlmClient::poll`]. Frames arriving while the rate limit or the
+/// in-flight cap holds requests back are not described.
+pub struct VlmClient {
+    settings: VlmSettings,
+    extractor: RoiExtractor,
+    runtime: Runtime,
+    sender: Sender<(Pending, io::Result<String>)>,
+    receiver: Receiver<(Pending, io::Result<String>)>,
+    in_flight: usize,
+    last_sent: Option<Instant>,
+}
+
+impl VlmClient {
+    pub fn new(settings: &VlmSettings) -> Result<Self> {
+        Url::parse(&settings.url).map_err(|e| VisionError::InvalidConfig(e.to_string()))?;
+        let runtime = runtime::Builder::new_multi_thread()
+            .worker_threads(1)
+            .enable_all()
+            .build()
+            .map_err(|e| VisionError::InvalidConfig(format!("unable to start the runtime for {}: {}", settings.url, e)))?;
+        let (sender, receiver) = mpsc::channel();
+        Ok(Self {
+            settings: settings.clone(),
+            extractor: RoiExtractor::new().with_padding(settings.padding),
+            runtime,
+            sender,
+            receiver,
+            in_flight: 0,
+            last_sent: None,
+        })
+    }
+
+    pub fn in_flight(&self) -> usize {
+        self.in_flight
+    }
+
+    // whether the rate limit and the in-flight cap let another request go
+    pub fn is_ready(&self) -> bool {
+        let interval = Duration::from_millis(self.settings.min_interval_ms);
+        self.in_flight < self.settings.max_in_flight
+            && match self.last_sent {
+                Some(sent) => sent.elapsed() >= interval,
+                None => true,
+            }
+    }
+
+    // sends the largest regions around the quarter resolution boxes, returning whether a
+    // request went out
+    pub fn submit(&mut self, source: &str, frame: u64, timestamp_ms: f64, boxes: &[Rect], video_frames: &VideoFrames) -> Result<bool> {
+        if !self.is_ready() {
+            return Ok(false);
+        }
+        let mut rois = self.extractor.extract(boxes, video_frames)?;
+        if rois.is_empty() {
+            return Ok(false);
+        }
+        rois.sort_by_key(|roi| -roi.region.area());
+        rois.truncate(self.settings.max_crops);
+
+        let mut images = Vec::with_capacity(rois.len());
+        for roi in &rois {
+            images.push(self.encode(&roi.image)?);
+        }
+        let pending = Pending {
+            source: source.to_string(),
+            frame,
+            timestamp_ms,
+            regions: rois.iter().map(|roi| BoundingBox::from(roi.region)).collect(),
+            sent: Instant::now(),
+        };
+
+        let settings = self.settings.clone();
+        let sender = self.sender.clone();
+        self.runtime.spawn(async move {
+            let reply = describe(&settings, &images).await;
+            // nobody is listening any more once the client is gone
+            sender.send((pending, reply)).ok();
+        });
+        self.in_flight += 1;
+        self.last_sent = Some(Instant::now());
+        Ok(true)
+    }
+
+    // the answers that came back since the last call, failed requests as errors
+    pub fn poll(&mut self) -> Vec<io::Result<Description>> {
+        let replies: Vec<_> = self.receiver.try_iter().collect();
+        self.in_flight -= replies.len();
+        replies.into_iter().map(into_description).collect()
+    }
+
+    // waits for the requests still in flight, each of which times out on its own
+    pub fn finish(&mut self) -> Vec<io::Result<Description>> {
+        let mut descriptions = Vec::new();
+        // a little longer than the request timeout, so that timeouts are reported as such
+        let timeout = Duration::from_millis(self.settings.timeout_ms) + Duration::from_secs(1);
+        while self.in_flight > 0 {
+            match self.receiver.recv_timeout(timeout) {
+                Ok(reply) => {
+                    self.in_flight -= 1;
+                    descriptions.push(into_description(reply));
+                }
+                Err(_) => break,
+            }
+        }
+        descriptions
+    }
+
+    fn encode(&self, image: &Mat) -> Result<String> {
+        let longest = image.cols().max(image.rows());
+        let mut resized = Mat::default();
+        let image = if longest > self.settings.max_crop_size {
+            let scale = self.settings.max_crop_size as f64 / longest as f64;
+            let size = Size::new(
+                ((image.cols() as f64 * scale).round() as i32).max(1),
+                ((image.rows() as f64 * scale).round() as i32).max(1),
+            );
+            imgproc::resize(image, &mut resized, size, 0.0, 0.0, imgproc::INTER_AREA)?;
+            &resized
+        } else {
+            image
+        };
+
+        let mut jpeg = Vector::<u8>::new();
+        let params = Vector::from_slice(&[imgcodecs::IMWRITE_JPEG_QUALITY, self.settings.jpeg_quality]);
+        imgcodecs::imencode(".jpg", image, &mut jpeg, &params)?;
+        Ok(base64(jpeg.as_slice()))
+    }
+}
+
+fn into_description((pending, reply): (Pending, io::Result<String>)) -> io::Result<Description> {
+    let description = reply?;
+    Ok(Description {
+        source: pending.source,
+        frame: pending.frame,
+        timestamp_ms: pending.timestamp_ms,
+        regions: pending.regions,
+        description,
+        latency_ms: pending.sent.elapsed().as_secs_f64() * 1000.0,
+    })
+}
+
+// asks the endpoint about the base64 encoded JPEG images and returns its answer
+pub async fn describe(settings: &VlmSettings, images: &[String]) -> io::Result<String> {
+    let url = Url::parse(&settings.url)?;
+    let mut headers = Vec::new();
+    if !settings.api_key.is_empty() {
+        headers.push((String::from("Authorization"), format!("Bearer {}", settings.api_key)));
+    }
+    let body = request_body(settings, images).to_string();
+
+    let response = http::post_json(&url, &headers, &body, Duration::from_millis(settings.timeout_ms)).await?;
+    if response.status != 200 {
+        return Err(io::Error::other(format!("{} answered {}: {}", settings.url, response.status, response.body.trim())));
+    }
+    reply_text(&response.body)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, format!("{} answered without a message", settings.url)))
+}
+
+// a single user message holding the prompt and the images
+fn request_body(settings: &VlmSettings, images: &[String]) -> Value {
+    match settings.api {
+        VlmApi::OpenAi => {
+            let mut content = vec![json!({ "type": "text", "text": settings.prompt })];
+            for image in images {
+                content.push(json!({
+                    "type": "image_url",
+                    "image_url": { "url": format!("data:image/jpeg;base64,{}", image) },
+                }));
+            }
+            json!({
+                "model": settings.model,
+                "messages": [{ "role": "user", "content": content }],
+            })
+        }
+        VlmApi::Ollama => json!({
+            "model": settings.model,
+            "stream": false,
+            "messages": [{ "role": "user", "content": settings.prompt, "images": images }],
+        }),
+    }
+}
+
+// the answer in either format, so that a mismatched api setting still works where it can
+fn reply_text(body: &str) -> Option<String> {
+    let reply: Value = serde_json::from_str(body).ok()?;
+    reply.pointer("/choices/0/message/content")
+        .or_else(|| reply.pointer("/message/content"))
+        .and_then(Value::as_str)
+        .map(|text| text.trim().to_string())
+}
+
+const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+// standard, padded base64
+fn base64(bytes: &[u8]) -> String {
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let group = chunk.iter()
+            .enumerate()
+            .fold(0u32, |group, (index, byte)| group | ((*byte as u32) << (16 - 8 * index)));
+        for index in 0..4 {
+            if index <= chunk.len() {
+                encoded.push(BASE64[((group >> (18 - 6 * index)) & 63) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use opencv::core::{self, Scalar};
+    use crate::util::http::serve_once;
+
+    fn settings(url: &str, api: VlmApi) -> VlmSettings {
+        VlmSettings {
+            url: url.to_string(),
+            api,
+            model: String::from("llava"),
+            api_key: String::from("secret"),
+            ..VlmSettings::default()
+        }
+    }
+
+    fn ok(body: &str) -> String {
+        format!("HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}", body.len(), body)
+    }
+
+    #[test]
+    fn encodes_base64() {
+        assert_eq!(base64(b""), "");
+        assert_eq!(base64(b"f"), "Zg==");
+        assert_eq!(base64(b"fo"), "Zm8=");
+        assert_eq!(base64(b"foobar"), "Zm9vYmFy");
+        assert_eq!(base64(&[0xff, 0xfe, 0x00, 0x3e]), "//4APg==");
+    }
+
+    #[tokio::test]
+    async fn describes_through_openai_and_ollama_endpoints() {
+        let reply = r#"{"choices":[{"index":0,"message":{"role":"assistant","content":" A cat crosses the lawn. "}}]}"#;
+        let (url, server) = serve_once("/v1/chat/completions", ok(reply)).await;
+        let images = vec![String::from("Zm9v"), String::from("YmFy")];
+        let description = describe(&settings(&url, VlmApi::OpenAi), &images).await.unwrap();
+        assert_eq!(description, "A cat crosses the lawn.");
+
+        let request = server.await.unwrap();
+        assert!(request.contains("Authorization: Bearer secret\r\n"), "{}", request);
+        let (_, body) = request.split_once("\r\n\r\n").unwrap();
+        let body: Value = serde_json::from_str(body).unwrap();
+        assert_eq!(body["model"], "llava");
+        assert_eq!(body["messages"][0]["content"][0]["text"], VlmSettings::default().prompt);
+        assert_eq!(body["messages"][0]["content"][2]["image_url"]["url"], "data:image/jpeg;base64,YmFy");
+
+        let reply = r#"{"model":"llava","message":{"role":"assistant","content":"A delivery van."},"done":true}"#;
+        let (url, server) = serve_once("/api/chat", ok(reply)).await;
+        let description = describe(&settings(&url, VlmApi::Ollama), &images).await.unwrap();
+        assert_eq!(description, "A delivery van.");
+
+        let request = server.await.unwrap();
+        let (_, body) = request.split_once("\r\n\r\n").unwrap();
+        let body: Value = serde_json::from_str(body).unwrap();
+        assert_eq!(body["stream"], false);
+        assert_eq!(body["messages"][0]["images"], json!(["Zm9v", "YmFy"]));
+    }
+
+    #[tokio::test]
+    async fn reports_failed_requests() {
+        let response = String::from("HTTP/1.1 404 Not Found\r\nContent-Length: 17\r\n\r\nmodel not found\r\n");
+        let (url, _server) = serve_once("/api/chat", response).await;
+        let error = describe(&settings(&url, VlmApi::Ollama), &[]).await.unwrap_err();
+        assert!(error.to_string().contains("404: model not found"), "{}", error);
+
+        let (url, _server) = serve_once("/api/chat", ok("{\"done\":true}")).await;
+        let error = describe(&settings(&url, VlmApi::Ollama), &[]).await.unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn client_holds_back_requests_within_the_interval() {
+        // the client blocks while it waits, so the server runs on a runtime of its own
+        let server_runtime = Runtime::new().unwrap();
+        let reply = r#"{"message":{"role":"assistant","content":"A person at the door."},"done":true}"#;
+        let (url, server) = server_runtime.block_on(serve_once("/api/chat", ok(reply)));
+        let mut client = VlmClient::new(&VlmSettings {
+            min_interval_ms: 60000,
+            max_in_flight: 2,
+            ..settings(&url, VlmApi::Ollama)
+        }).unwrap();
+
+        let mut video_frames = VideoFrames::new(Size::new(1280, 720), Size::new(640, 360), Size::new(320, 180)).unwrap();
+        let frame = Mat::new_size_with_default(Size::new(1280, 720), core::CV_8UC3, Scalar::all(200.0)).unwrap();
+        video_frames.load_frame(frame).unwrap();
+        let boxes = vec![Rect::new(10, 10, 20, 20)];
+
+        assert!(client.submit("cam.mp4", 120, 4800.0, &boxes, &video_frames).unwrap());
+        assert!(!client.submit("cam.mp4", 121, 4840.0, &boxes, &video_frames).unwrap());
+        assert_eq!(client.in_flight(), 1);
+
+        let descriptions: Vec<Description> = client.finish().into_iter().map(|reply| reply.unwrap()).collect();
+        assert_eq!(client.in_flight(), 0);
+        assert_eq!(descriptions.len(), 1);
+        assert_eq!(descriptions[0].frame, 120);
+        assert_eq!(descriptions[0].regions.len(), 1);
+        assert_eq!(descriptions[0].description, "A person at the door.");
+        assert!(client.poll().is_empty());
+
+        let request = server_runtime.block_on(server).unwrap();
+        assert!(request.contains("\"images\":[\""), "{}", request);
+    }
+}
@@ -0,0 +1,214 @@
+use std::collections::VecDeque;
+use std::fs;
+use std::path::{Path, PathBuf};
+use chrono::Local;
+use opencv::core::Mat;
+use crate::config::ClipSettings;
+use crate::error::{Result, VisionError};
+use crate::sinks::video_output::VideoOutput;
+use crate::sources::frame_source::FrameSource;
+
+/// Records motion triggered clips at full resolution. The most recent frames are kept in a
+/// pre-roll ring buffer so that a clip starts a little before the motion did, and recording
+/// carries on for a post-roll after the last motion. Clips are named after the source and the
+/// time the motion started.
+pub struct ClipRecorder {
+    directory: PathBuf,
+    label: String,
+    live: bool,
+    codec: String,
+    container: String,
+    fps: f64,
+    pre_roll: VecDeque<Mat>,
+    pre_roll_capacity: usize,
+    post_roll_ms: f64,
+    clip: Option<VideoOutput>,
+    last_motion_ms: f64,
+    clips: Vec<PathBuf>,
+}
+
+impl ClipRecorder {
+    pub fn new(settings: &ClipSettings, source: &dyn FrameSource, fps: f64) -> Result<Self> {
+        let directory = PathBuf::from(&settings.directory);
+        fs::create_dir_all(&directory).map_err(|e| VisionError::sink_open(&settings.directory, e))?;
+
+        // the frame that triggers a clip is buffered too, so there is always room for one
+        let pre_roll_capacity = ((settings.pre_roll_ms as f64 / 1000.0 * fps).ceil() as usize).max(1);
+
+        Ok(Self {
+            directory,
+            label: clip_label(source.name()),
+            live: source.is_live(),
+            codec: settings.codec.clone(),
+            container: settings.container.clone(),
+            fps,
+            pre_roll: VecDeque::with_capacity(pre_roll_capacity),
+            pre_roll_capacity,
+            post_roll_ms: settings.post_roll_ms as f64,
+            clip: None,
+            last_motion_ms: 0.0,
+            clips: Vec::new(),
+        })
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.clip.is_some()
+    }
+
+    // every clip started so far, including the one being recorded
+    pub fn clips(&self) -> &Vec<PathBuf> {
+        &self.clips
+    }
+
+    // hands over the next frame, which is written to the open clip or kept for the pre-roll
+    pub fn push(&mut self, frame: Mat, timestamp_ms: f64) -> Result<()> {
+        if let Some(clip) = self.clip.as_mut() {
+            if timestamp_ms - self.last_motion_ms <= self.post_roll_ms {
+                return clip.write(&frame);
+            }
+            self.finish()?;
+        }
+
+        if self.pre_roll.len() == self.pre_roll_capacity {
+            self.pre_roll.pop_front();
+        }
+        self.pre_roll.push_back(frame);
+        Ok(())
+    }
+
+    // marks motion in the frame last pushed, starting a clip or extending the post-roll
+    pub fn motion(&mut self, timestamp_ms: f64) -> Result<()> {
+        self.last_motion_ms = timestamp_ms;
+        if self.clip.is_some() {
+            return Ok(());
+        }
+
+        let path = self.clip_path(timestamp_ms);
+        let mut clip = VideoOutput::new(&path.to_string_lossy(), &self.codec, self.fps)?;
+        for frame in self.pre_roll.drain(..) {
+            clip.write(&frame)?;
+        }
+        self.clips.push(path);
+        self.clip = Some(clip);
+        Ok(())
+    }
+
+    // closes the clip being recorded, if any
+    pub fn finish(&mut self) -> Result<()> {
+        if let Some(mut clip) = self.clip.take() {
+            clip.release()?;
+        }
+        Ok(())
+    }
+
+    fn clip_path(&self, timestamp_ms: f64) -> PathBuf {
+        // live sources are named by wall clock time, anything else by the position in the media
+        let timestamp = if self.live {
+            Local::now().format("%Y%m%d-%H%M%S").to_string()
+        } else {
+            format_position(timestamp_ms)
+        };
+
+        let stem = format!("{}_{}", self.label, timestamp);
+        let mut path = self.directory.join(format!("{}.{}", stem, self.container));
+        let mut index = 1;
+        while path.exists() || self.clips.contains(&path) {
+            path = self.directory.join(format!("{}_{}.{}", stem, index, self.container));
+            index += 1;
+        }
+        path
+    }
+}
+
+// a file name friendly label for a source: the file stem of a path, or the host and path of a URL
+pub fn clip_label(name: &str) -> String {
+    let label = match name.split_once("://") {
+        Some((_, rest)) => rest.to_string(),
+        None => Path::new(name)
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_else(|| name.to_string()),
+    };
+
+    let label: String = label.chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+        .collect();
+    let label = label.trim_matches('_');
+    if label.is_empty() { String::from("source") } else { label.to_string() }
+}
+
+fn format_position(timestamp_ms: f64) -> String {
+    let total_ms = timestamp_ms.max(0.0).round() as u64;
+    format!(
+        "{:02}h{:02}m{:02}s{:03}",
+        total_ms / 3_600_000,
+        total_ms / 60_000 % 60,
+        total_ms / 1000 % 60,
+        total_ms % 1000,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use opencv::core::{Scalar, Size, CV_8UC3};
+    use crate::sources::file_source::FileSource;
+    use crate::sources::memory_source::MemorySource;
+
+    fn settings(directory: &Path) -> ClipSettings {
+        ClipSettings {
+            directory: directory.to_string_lossy().into_owned(),
+            pre_roll_ms: 300,
+            post_roll_ms: 200,
+            codec: String::from("MJPG"),
+            container: String::from("avi"),
+        }
+    }
+
+    fn frame() -> Mat {
+        Mat::new_size_with_default(Size::new(160, 120), CV_8UC3, Scalar::all(100.0)).unwrap()
+    }
+
+    fn frame_count(path: &Path) -> usize {
+        let mut source = FileSource::open(&path.to_string_lossy()).unwrap();
+        let mut frame = Mat::default();
+        let mut frames = 0;
+        while source.read(&mut frame).unwrap() {
+            frames += 1;
+        }
+        frames
+    }
+
+    #[test]
+    fn clip_holds_pre_roll_and_post_roll() {
+        let directory = env::temp_dir().join(format!("rusty_vision_clips_{}", std::process::id()));
+        let source = MemorySource::new(vec![frame()], 10.0);
+        let mut recorder = ClipRecorder::new(&settings(&directory), &source, 10.0).unwrap();
+
+        // 10 fps: motion at frame 10 and again at 12, which extends the post-roll to frame 14
+        for index in 0..20 {
+            let timestamp_ms = index as f64 * 100.0;
+            recorder.push(frame(), timestamp_ms).unwrap();
+            if index == 10 || index == 12 {
+                recorder.motion(timestamp_ms).unwrap();
+            }
+        }
+        recorder.finish().unwrap();
+
+        assert_eq!(recorder.clips().len(), 1);
+        let clip = &recorder.clips()[0];
+        assert_eq!(clip.file_name().unwrap(), "memory_00h00m01s000.avi");
+        // frames 8-10 from the pre-roll, then 11-14 until the post-roll ran out
+        assert_eq!(frame_count(clip), 7);
+        fs::remove_dir_all(directory).ok();
+    }
+
+    #[test]
+    fn labels_are_file_name_friendly() {
+        assert_eq!(clip_label("videos/front door.mp4"), "front_door");
+        assert_eq!(clip_label("rtsp://10.0.0.2:8554/cam"), "10_0_0_2_8554_cam");
+        assert_eq!(clip_label("/dev/video0"), "video0");
+        assert_eq!(format_position(3_725_042.0), "01h02m05s042");
+    }
+}
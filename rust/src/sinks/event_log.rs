@@ -0,0 +1,405 @@
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use opencv::core::Rect;
+use serde::{Deserialize, Serialize};
+use crate::config::ZoneKind;
+use crate::detectors::motion_events::MotionEvent;
+use crate::error::{Result, VisionError};
+use crate::masks::zones::ZoneEvent;
+use crate::roi::vlm::Description;
+use crate::tracking::loitering::Loitering;
+use crate::tracking::tripwire::Crossing;
+
+/// A bounding box in full resolution pixel coordinates.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BoundingBox {
+    pub x: i32,
+    pub y: i32,
+    pub width: i32,
+    pub height: i32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub track_id: Option<u64>,          // set once the box belongs to a confirmed track
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,          // set when the classifier recognised the box
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub confidence: Option<f64>,
+}
+
+impl From<Rect> for BoundingBox {
+    fn from(rect: Rect) -> Self {
+        Self {
+            x: rect.x,
+            y: rect.y,
+            width: rect.width,
+            height: rect.height,
+            track_id: None,
+            label: None,
+            confidence: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EventKind {
+    Start,
+    Motion,
+    End,
+    Crossing,
+    ZoneStart,
+    ZoneEnd,
+    Loitering,
+    Description,
+}
+
+/// What the detectors found in one processed frame.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FrameRecord {
+    pub source: String,
+    pub frame: u64,
+    pub timestamp_ms: f64,
+    #[serde(flatten)]
+    pub scores: BTreeMap<String, Option<f64>>, // e.g. mse_avg, per cascade stage; None when an earlier stage skipped it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub shadow_fraction: Option<f64>,   // set when the segmenting stage detects shadows
+    pub boxes: Vec<BoundingBox>,
+}
+
+#[derive(Serialize)]
+struct EventLine<'a> {
+    event: EventKind,
+    #[serde(flatten)]
+    frame: &'a FrameRecord,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    duration_ms: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    mean_shadow_fraction: Option<f64>,  // over the frames of the event, on its end
+}
+
+#[derive(Serialize)]
+struct CrossingLine<'a> {
+    event: EventKind,
+    source: &'a str,
+    frame: u64,
+    timestamp_ms: f64,
+    #[serde(flatten)]
+    crossing: &'a Crossing,
+}
+
+#[derive(Serialize)]
+struct LoiteringLine<'a> {
+    event: EventKind,
+    source: &'a str,
+    frame: u64,
+    timestamp_ms: f64,
+    #[serde(flatten)]
+    loitering: &'a Loitering,
+}
+
+#[derive(Serialize)]
+struct DescriptionLine<'a> {
+    event: EventKind,
+    #[serde(flatten)]
+    description: &'a Description,
+}
+
+#[derive(Serialize)]
+struct ZoneLine<'a> {
+    event: EventKind,
+    source: &'a str,
+    frame: u64,
+    timestamp_ms: f64,
+    zone: &'a str,
+    kind: ZoneKind,
+    area: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    duration_ms: Option<f64>,
+}
+
+/// Writes motion events as JSON Lines, one object per processed frame during an event plus one
+/// when it ends, to a file or (for `-`) to stdout. Tripwire crossings, the start and end of
+/// motion in each zone and loitering tracks are logged as they happen, descriptions of the
+/// motion as they come back from the vision-language model. The end of an event carries the
+/// mean shadow fraction of its frames when shadows are detected.
+pub struct EventLog {
+    writer: Box<dyn Write>,
+    path: String,
+    shadow_fractions: Vec<f64>,         // of the frames of the current event
+}
+
+impl EventLog {
+    pub fn open(path: &str) -> Result<Self> {
+        let writer: Box<dyn Write> = if path == "-" {
+            Box::new(io::stdout())
+        } else {
+            let file = File::create(path).map_err(|e| VisionError::sink_open(path, e))?;
+            Box::new(BufWriter::new(file))
+        };
+        Ok(Self {
+            writer,
+            path: path.to_string(),
+            shadow_fractions: Vec::new(),
+        })
+    }
+
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    // logs a processed frame; outside of an event only the frame that ends one is logged
+    pub fn update(&mut self, event: Option<&MotionEvent>, active: bool, frame: &FrameRecord) -> Result<()> {
+        match event {
+            Some(MotionEvent::MotionStarted { .. }) => {
+                self.shadow_fractions = frame.shadow_fraction.into_iter().collect();
+                self.write(EventKind::Start, frame)
+            }
+            Some(MotionEvent::MotionEnded { duration_ms, .. }) => {
+                let shadow_fractions = std::mem::take(&mut self.shadow_fractions);
+                let mean_shadow_fraction = if shadow_fractions.is_empty() {
+                    None
+                } else {
+                    Some(shadow_fractions.iter().sum::<f64>() / shadow_fractions.len() as f64)
+                };
+                self.write_line(&EventLine {
+                    event: EventKind::End,
+                    frame,
+                    duration_ms: Some(*duration_ms),
+                    mean_shadow_fraction,
+                })
+            }
+            None if active => {
+                self.shadow_fractions.extend(frame.shadow_fraction);
+                self.write(EventKind::Motion, frame)
+            }
+            None => Ok(()),
+        }
+    }
+
+    pub fn crossing(&mut self, crossing: &Crossing, frame: &FrameRecord) -> Result<()> {
+        self.write_line(&CrossingLine {
+            event: EventKind::Crossing,
+            source: &frame.source,
+            frame: frame.frame,
+            timestamp_ms: frame.timestamp_ms,
+            crossing,
+        })
+    }
+
+    pub fn loitering(&mut self, loitering: &Loitering, frame: &FrameRecord) -> Result<()> {
+        self.write_line(&LoiteringLine {
+            event: EventKind::Loitering,
+            source: &frame.source,
+            frame: frame.frame,
+            timestamp_ms: frame.timestamp_ms,
+            loitering,
+        })
+    }
+
+    // carries the frame the described crops were taken from, which was logged some time before
+    pub fn description(&mut self, description: &Description) -> Result<()> {
+        self.write_line(&DescriptionLine {
+            event: EventKind::Description,
+            description,
+        })
+    }
+
+    pub fn zone(&mut self, zone_event: &ZoneEvent, frame: &FrameRecord) -> Result<()> {
+        let (event, duration_ms) = match zone_event.event {
+            MotionEvent::MotionStarted { .. } => (EventKind::ZoneStart, None),
+            MotionEvent::MotionEnded { duration_ms, .. } => (EventKind::ZoneEnd, Some(duration_ms)),
+        };
+        self.write_line(&ZoneLine {
+            event,
+            source: &frame.source,
+            frame: frame.frame,
+            timestamp_ms: frame.timestamp_ms,
+            zone: &zone_event.zone,
+            kind: zone_event.kind,
+            area: zone_event.area,
+            duration_ms,
+        })
+    }
+
+    pub fn finish(&mut self) -> Result<()> {
+        self.writer.flush().map_err(|e| VisionError::sink_open(&self.path, e))
+    }
+
+    fn write(&mut self, event: EventKind, frame: &FrameRecord) -> Result<()> {
+        self.write_line(&EventLine {
+            event,
+            frame,
+            duration_ms: None,
+            mean_shadow_fraction: None,
+        })
+    }
+
+    fn write_line<T: Serialize>(&mut self, line: &T) -> Result<()> {
+        let mut line = serde_json::to_string(line)
+            .map_err(|e| VisionError::sink_open(&self.path, e))?;
+        line.push('\n');
+
+        // flushed per event so that the log can be followed while it is written
+        self.writer.write_all(line.as_bytes())
+            .and_then(|_| self.writer.flush())
+            .map_err(|e| VisionError::sink_open(&self.path, e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::fs;
+    use serde_json::Value;
+    use crate::config::MotionEventSettings;
+    use crate::detectors::motion_events::MotionEvents;
+    use crate::tracking::tripwire::CrossingDirection;
+
+    fn frame(index: u64, motion: bool) -> FrameRecord {
+        FrameRecord {
+            source: String::from("clip.mp4"),
+            frame: index,
+            timestamp_ms: index as f64 * 100.0,
+            scores: BTreeMap::from([
+                (String::from("mse_avg"), Some(if motion { 1.5 } else { 0.1 })),
+                (String::from("mog2_avg"), if motion { Some(8000.0) } else { None }),
+            ]),
+            shadow_fraction: if motion { Some(index as f64 / 10.0) } else { None },
+            boxes: if motion { vec![BoundingBox::from(Rect::new(40, 80, 320, 240))] } else { Vec::new() },
+        }
+    }
+
+    // the lines `write` logs, read back before any assertion so the temp file is always removed
+    fn logged(name: &str, write: impl FnOnce(&mut EventLog)) -> Vec<Value> {
+        let path = env::temp_dir().join(format!("rusty_vision_{}_{}.jsonl", name, std::process::id()));
+        let mut event_log = EventLog::open(path.to_str().unwrap()).unwrap();
+        write(&mut event_log);
+        event_log.finish().unwrap();
+        drop(event_log);
+
+        let contents = fs::read_to_string(&path);
+        fs::remove_file(&path).ok();
+        contents.unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn logs_start_motion_and_end() {
+        let lines = logged("events", |event_log| {
+            let mut motion_events = MotionEvents::new(5000.0, &MotionEventSettings::default());
+            for (index, motion) in [false, true, true, false, false, true].into_iter().enumerate() {
+                let record = frame(index as u64, motion);
+                let event = motion_events.update(record.frame, record.timestamp_ms, record.scores["mog2_avg"]);
+                event_log.update(event.as_ref(), motion_events.is_active(), &record).unwrap();
+            }
+            let record = frame(6, false);
+            let event = motion_events.finish(record.frame, record.timestamp_ms);
+            event_log.update(event.as_ref(), false, &record).unwrap();
+        });
+
+        let events: Vec<&str> = lines.iter().map(|line| line["event"].as_str().unwrap()).collect();
+        assert_eq!(events, vec!["start", "motion", "end", "start", "end"]);
+
+        assert_eq!(lines[0]["source"], "clip.mp4");
+        assert_eq!(lines[0]["frame"], 1);
+        assert_eq!(lines[0]["timestamp_ms"], 100.0);
+        assert_eq!(lines[0]["mog2_avg"], 8000.0);
+        assert_eq!(lines[0]["boxes"][0]["width"], 320);
+        assert!(lines[0]["boxes"][0].get("track_id").is_none());
+        assert!(lines[0]["boxes"][0].get("label").is_none());
+        assert!(lines[0].get("duration_ms").is_none());
+        assert_eq!(lines[0]["shadow_fraction"], 0.1);
+        assert!(lines[0].get("mean_shadow_fraction").is_none());
+
+        assert_eq!(lines[2]["frame"], 3);
+        assert_eq!(lines[2]["duration_ms"], 200.0);
+        assert!(lines[2]["mog2_avg"].is_null());
+        assert!(lines[2].get("shadow_fraction").is_none());
+        assert!((lines[2]["mean_shadow_fraction"].as_f64().unwrap() - 0.15).abs() < 1e-9);
+        assert_eq!(lines[4]["frame"], 6);
+    }
+
+    #[test]
+    fn logs_crossings_with_the_counts() {
+        let crossing = Crossing {
+            line: String::from("door"),
+            track_id: 7,
+            direction: CrossingDirection::BToA,
+            a_to_b: 3,
+            b_to_a: 1,
+        };
+        let lines = logged("crossings", |event_log| event_log.crossing(&crossing, &frame(12, true)).unwrap());
+        let line = &lines[0];
+        assert_eq!(line["event"], "crossing");
+        assert_eq!(line["frame"], 12);
+        assert_eq!(line["line"], "door");
+        assert_eq!(line["track_id"], 7);
+        assert_eq!(line["direction"], "b_to_a");
+        assert_eq!(line["a_to_b"], 3);
+        assert!(line.get("boxes").is_none());
+    }
+
+    #[test]
+    fn logs_zone_events() {
+        let lines = logged("zones", |event_log| {
+            event_log.zone(&ZoneEvent {
+                zone: String::from("driveway"),
+                kind: ZoneKind::Include,
+                area: Some(640.0),
+                event: MotionEvent::MotionStarted { frame: 4, timestamp_ms: 400.0 },
+            }, &frame(4, true)).unwrap();
+            event_log.zone(&ZoneEvent {
+                zone: String::from("driveway"),
+                kind: ZoneKind::Include,
+                area: None,
+                event: MotionEvent::MotionEnded { frame: 9, timestamp_ms: 900.0, duration_ms: 500.0 },
+            }, &frame(9, false)).unwrap();
+        });
+        assert_eq!(lines[0]["event"], "zone_start");
+        assert_eq!(lines[0]["zone"], "driveway");
+        assert_eq!(lines[0]["kind"], "include");
+        assert_eq!(lines[0]["area"], 640.0);
+        assert!(lines[0].get("duration_ms").is_none());
+        assert_eq!(lines[1]["event"], "zone_end");
+        assert_eq!(lines[1]["frame"], 9);
+        assert!(lines[1]["area"].is_null());
+        assert_eq!(lines[1]["duration_ms"], 500.0);
+    }
+
+    #[test]
+    fn logs_loitering_tracks() {
+        let loitering = Loitering {
+            zone: String::from("porch"),
+            track_id: 3,
+            duration_ms: 30000.0,
+        };
+        let lines = logged("loitering", |event_log| event_log.loitering(&loitering, &frame(900, true)).unwrap());
+        let line = &lines[0];
+        assert_eq!(line["event"], "loitering");
+        assert_eq!(line["frame"], 900);
+        assert_eq!(line["zone"], "porch");
+        assert_eq!(line["track_id"], 3);
+        assert_eq!(line["duration_ms"], 30000.0);
+    }
+
+    #[test]
+    fn logs_descriptions() {
+        let description = Description {
+            source: String::from("cam.mp4"),
+            frame: 120,
+            timestamp_ms: 4800.0,
+            regions: vec![BoundingBox::from(Rect::new(40, 80, 200, 320))],
+            description: String::from("A person walks up to the door."),
+            latency_ms: 1500.0,
+        };
+        let lines = logged("description", |event_log| event_log.description(&description).unwrap());
+        let line = &lines[0];
+        assert_eq!(line["event"], "description");
+        assert_eq!(line["frame"], 120);
+        assert_eq!(line["regions"][0]["height"], 320);
+        assert_eq!(line["description"], "A person walks up to the door.");
+    }
+}
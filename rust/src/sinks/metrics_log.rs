@@ -0,0 +1,168 @@
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::time::Duration;
+use crate::error::{Result, VisionError};
+
+/// Stopwatch laps that get a column of their own, in column order. Laps with other labels
+/// only count towards `total_ms`.
+pub const STAGES: &[&str] = &["Read Frame", "MSE", "MOG2", "KNN", "Track", "Classify", "Event Log", "Record Clip", "Overlay", "Write Output"];
+
+/// The detector signals and stage timings of one processed frame.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FrameMetrics<'a> {
+    pub source: &'a str,
+    pub frame: u64,
+    pub timestamp_ms: f64,
+    pub detectors: &'a [Option<(f64, f64)>], // value and score per cascade stage, None when it was skipped
+    pub boxes: usize,
+    pub motion: bool,
+    pub laps: &'a [(Duration, String)],
+}
+
+/// Writes one CSV row per processed frame, for plotting the detector signals when choosing
+/// thresholds. Each detector of the cascade gets a column for its value and one for its score,
+/// e.g. `mog2_area` and `mog2_area_avg`.
+pub struct MetricsLog {
+    writer: BufWriter<File>,
+    path: String,
+}
+
+impl MetricsLog {
+    // `columns` names the value of each detector, its score goes in the same column with `_avg`
+    pub fn open(path: &str, columns: &[String]) -> Result<Self> {
+        let file = File::create(path).map_err(|e| VisionError::sink_open(path, e))?;
+        let mut metrics_log = Self {
+            writer: BufWriter::new(file),
+            path: path.to_string(),
+        };
+
+        let mut header = vec![
+            String::from("source"),
+            String::from("frame"),
+            String::from("timestamp_ms"),
+        ];
+        for column in columns {
+            header.push(column.clone());
+            header.push(format!("{}_avg", column));
+        }
+        header.push(String::from("boxes"));
+        header.push(String::from("motion"));
+        header.extend(STAGES.iter().map(|stage| column(stage)));
+        header.push(String::from("total_ms"));
+        metrics_log.write_row(&header)?;
+        Ok(metrics_log)
+    }
+
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    pub fn write(&mut self, metrics: &FrameMetrics) -> Result<()> {
+        let optional = |value: Option<f64>| value.map(|value| value.to_string()).unwrap_or_default();
+
+        let mut row = vec![
+            escape(metrics.source),
+            metrics.frame.to_string(),
+            format!("{:.1}", metrics.timestamp_ms),
+        ];
+        for detector in metrics.detectors {
+            row.push(optional(detector.map(|(value, _)| value)));
+            row.push(optional(detector.map(|(_, score)| score)));
+        }
+        row.push(metrics.boxes.to_string());
+        row.push((metrics.motion as u8).to_string());
+        // a stage timed more than once in a frame is summed, one that did not run is left empty
+        for stage in STAGES {
+            let durations: Vec<Duration> = metrics.laps.iter()
+                .filter(|(_, label)| label == stage)
+                .map(|(duration, _)| *duration)
+                .collect();
+            row.push(if durations.is_empty() {
+                String::new()
+            } else {
+                milliseconds(durations.iter().sum())
+            });
+        }
+        row.push(milliseconds(metrics.laps.iter().map(|(duration, _)| *duration).sum()));
+        self.write_row(&row)
+    }
+
+    pub fn finish(&mut self) -> Result<()> {
+        self.writer.flush().map_err(|e| VisionError::sink_open(&self.path, e))
+    }
+
+    fn write_row(&mut self, fields: &[String]) -> Result<()> {
+        writeln!(self.writer, "{}", fields.join(",")).map_err(|e| VisionError::sink_open(&self.path, e))
+    }
+}
+
+// "Record Clip" => "record_clip_ms"
+fn column(stage: &str) -> String {
+    format!("{}_ms", stage.to_ascii_lowercase().replace(' ', "_"))
+}
+
+fn milliseconds(duration: Duration) -> String {
+    format!("{:.3}", duration.as_secs_f64() * 1000.0)
+}
+
+// quotes a field holding a separator, quote or line break, doubling any quotes in it
+fn escape(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::fs;
+
+    #[test]
+    fn writes_a_row_per_frame_with_stage_columns() {
+        let path = env::temp_dir().join(format!("rusty_vision_metrics_{}.csv", std::process::id()));
+        let mut metrics_log = MetricsLog::open(path.to_str().unwrap(), &[String::from("mse"), String::from("mog2_area")]).unwrap();
+
+        let gated = vec![
+            (Duration::from_micros(1500), String::from("Read Frame")),
+            (Duration::from_micros(250), String::from("MSE")),
+        ];
+        let mut moving = gated.clone();
+        moving.push((Duration::from_millis(4), String::from("MOG2")));
+        moving.push((Duration::from_millis(2), String::from("Overlay")));
+        moving.push((Duration::from_millis(1), String::from("Overlay")));
+
+        metrics_log.write(&FrameMetrics {
+            source: "front door, east.mp4",
+            frame: 15,
+            timestamp_ms: 500.0,
+            detectors: &[Some((0.125, 0.25)), None],
+            boxes: 0,
+            motion: false,
+            laps: &gated,
+        }).unwrap();
+        metrics_log.write(&FrameMetrics {
+            source: "front door, east.mp4",
+            frame: 30,
+            timestamp_ms: 1000.0,
+            detectors: &[Some((1.5, 0.75)), Some((9000.0, 6000.0))],
+            boxes: 2,
+            motion: true,
+            laps: &moving,
+        }).unwrap();
+        metrics_log.finish().unwrap();
+
+        let csv = fs::read_to_string(&path).unwrap();
+        fs::remove_file(&path).ok();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(
+            lines[0],
+            "source,frame,timestamp_ms,mse,mse_avg,mog2_area,mog2_area_avg,boxes,motion,\
+             read_frame_ms,mse_ms,mog2_ms,knn_ms,track_ms,classify_ms,event_log_ms,record_clip_ms,overlay_ms,write_output_ms,total_ms"
+        );
+        assert_eq!(lines[1], "\"front door, east.mp4\",15,500.0,0.125,0.25,,,0,0,1.500,0.250,,,,,,,,,1.750");
+        assert_eq!(lines[2], "\"front door, east.mp4\",30,1000.0,1.5,0.75,9000,6000,2,1,1.500,0.250,4.000,,,,,,3.000,,8.750");
+    }
+}
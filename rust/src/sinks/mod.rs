@@ -0,0 +1,9 @@
+//! Outputs of the processing loop: annotated video, event clips and logs.
+
+pub mod video_output;
+
+pub mod clip_recorder;
+
+pub mod event_log;
+
+pub mod metrics_log;
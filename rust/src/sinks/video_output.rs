@@ -0,0 +1,130 @@
+use opencv::core::{Mat, MatTraitConst, Size};
+use opencv::imgproc;
+use opencv::prelude::{VideoWriterTrait, VideoWriterTraitConst};
+use opencv::videoio::VideoWriter;
+use crate::error::{Result, VisionError};
+
+/// Writes frames to a video file through OpenCV's `VideoWriter`. The container is picked from
+/// the file extension and the codec from a FourCC such as `mp4v` or `MJPG`. The file is only
+/// opened once the first frame, and with it the frame size, is known.
+pub struct VideoOutput {
+    writer: Option<VideoWriter>,
+    path: String,
+    fourcc: i32,
+    fps: f64,
+    size: Size,
+    frame_count: usize,
+}
+
+impl VideoOutput {
+    pub fn new(path: &str, codec: &str, fps: f64) -> Result<Self> {
+        if fps <= 0.0 {
+            return Err(VisionError::InvalidConfig(format!("output frame rate must be positive, got {}", fps)));
+        }
+        Ok(Self {
+            writer: None,
+            path: path.to_string(),
+            fourcc: fourcc(codec)?,
+            fps,
+            size: Size::default(),
+            frame_count: 0,
+        })
+    }
+
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    pub fn frame_count(&self) -> usize {
+        self.frame_count
+    }
+
+    fn open(&mut self, size: Size) -> Result<VideoWriter> {
+        let writer = VideoWriter::new(&self.path, self.fourcc, self.fps, size, true)
+            .map_err(|e| VisionError::sink_open(&self.path, e))?;
+        if !writer.is_opened()? {
+            return Err(VisionError::sink_open(&self.path, "codec or container not supported"));
+        }
+        self.size = size;
+        Ok(writer)
+    }
+
+    // frames of a different size than the first are scaled to match, a video has a single size
+    pub fn write(&mut self, frame: &Mat) -> Result<()> {
+        let writer = match self.writer.take() {
+            Some(writer) => writer,
+            None => self.open(frame.size()?)?,
+        };
+        let writer = self.writer.insert(writer);
+
+        if frame.size()? == self.size {
+            writer.write(frame)?;
+        } else {
+            let mut resized = Mat::default();
+            imgproc::resize(frame, &mut resized, self.size, 0.0, 0.0, imgproc::INTER_LINEAR)?;
+            writer.write(&resized)?;
+        }
+        self.frame_count += 1;
+        Ok(())
+    }
+
+    // finalises the file; dropping the output does the same
+    pub fn release(&mut self) -> Result<()> {
+        if let Some(mut writer) = self.writer.take() {
+            writer.release()?;
+        }
+        Ok(())
+    }
+}
+
+pub fn fourcc(codec: &str) -> Result<i32> {
+    let chars: Vec<char> = codec.chars().collect();
+    match chars[..] {
+        [c1, c2, c3, c4] if codec.is_ascii() => Ok(VideoWriter::fourcc(c1, c2, c3, c4)?),
+        _ => Err(VisionError::InvalidConfig(format!(
+            "codec must be a four character code such as mp4v or MJPG, got '{}'",
+            codec
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::fs;
+    use opencv::core::{Scalar, CV_8UC3};
+    use crate::sources::file_source::FileSource;
+    use crate::sources::frame_source::FrameSource;
+
+    #[test]
+    fn writes_every_frame() {
+        let path = env::temp_dir().join(format!("rusty_vision_output_{}.avi", std::process::id()));
+        let path = path.to_str().unwrap();
+
+        let mut output = VideoOutput::new(path, "MJPG", 10.0).unwrap();
+        let frame = Mat::new_size_with_default(Size::new(320, 240), CV_8UC3, Scalar::all(128.0)).unwrap();
+        for _ in 0..5 {
+            output.write(&frame).unwrap();
+        }
+        output.release().unwrap();
+        assert_eq!(output.frame_count(), 5);
+
+        let mut source = FileSource::open(path).unwrap();
+        assert_eq!(source.size(), Size::new(320, 240));
+        let mut frames = 0;
+        let mut read = Mat::default();
+        while source.read(&mut read).unwrap() {
+            frames += 1;
+        }
+        assert_eq!(frames, 5);
+        fs::remove_file(path).ok();
+    }
+
+    #[test]
+    fn rejects_codecs_that_are_not_fourcc() {
+        assert!(fourcc("mp4v").is_ok());
+        assert!(matches!(VideoOutput::new("out.mp4", "h264x", 10.0), Err(VisionError::InvalidConfig(_))));
+        assert!(matches!(VideoOutput::new("out.mp4", "mp4v", 0.0), Err(VisionError::InvalidConfig(_))));
+    }
+}
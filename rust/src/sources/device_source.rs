@@ -0,0 +1,107 @@
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+use opencv::core::{Mat, MatTraitConst, Size};
+use opencv::prelude::{VideoCaptureTrait, VideoCaptureTraitConst};
+use opencv::videoio::{self, VideoCapture};
+use crate::error::{Result, VisionError};
+use crate::sources::frame_source::{capture_size, FrameSource};
+
+// delay between read attempts after a live device drops a frame
+const DROPPED_FRAME_DELAY: Duration = Duration::from_millis(100);
+
+/// A local camera, opened by index or by (V4L2) device path, which retries dropped reads.
+pub struct DeviceSource {
+    cam: VideoCapture,
+    name: String,
+    size: Size,
+    fps: f64,
+    max_dropped_frames: i32,
+    dropped_frame_count: i32,
+    opened_at: Instant,
+    timestamp_ms: f64,
+    end_of_stream: bool,
+}
+
+impl DeviceSource {
+    pub fn open(device: &str, max_dropped_frames: i32) -> Result<Self> {
+
+        // a bare number is a camera index, anything else is a device path (e.g. a
+        // v4l2loopback device such as /dev/video10), which is opened through V4L2
+        let (cam, name) = match device.parse::<i32>() {
+            Ok(index) => (VideoCapture::new(index, videoio::CAP_ANY), format!("camera{}", index)),
+            Err(_) => (VideoCapture::from_file(device, videoio::CAP_V4L2), device.to_string()),
+        };
+        let cam = cam.map_err(|e| VisionError::source_open(device, e))?;
+
+        // Check if video device opened successfully
+        if !cam.is_opened()? {
+            return Err(VisionError::source_open(device, "no such video device, or it is busy"));
+        }
+
+        Ok(Self {
+            size: capture_size(&cam)?,
+            fps: cam.get(videoio::CAP_PROP_FPS)?,
+            cam,
+            name,
+            max_dropped_frames,
+            dropped_frame_count: 0,
+            opened_at: Instant::now(),
+            timestamp_ms: 0.0,
+            end_of_stream: false,
+        })
+    }
+
+    pub fn dropped_frame_count(&self) -> i32 {
+        self.dropped_frame_count
+    }
+}
+
+impl FrameSource for DeviceSource {
+    fn read(&mut self, frame: &mut Mat) -> Result<bool> {
+        while !self.end_of_stream {
+            if self.cam.read(frame).unwrap_or(false) && !frame.empty() {
+                self.dropped_frame_count = 0;
+                self.timestamp_ms = self.opened_at.elapsed().as_secs_f64() * 1000.0;
+                return Ok(true);
+            }
+
+            // live devices drop reads now and then, so wait and try again
+            if self.dropped_frame_count >= self.max_dropped_frames {
+                self.end_of_stream = true;
+                break;
+            }
+            self.dropped_frame_count += 1;
+            sleep(DROPPED_FRAME_DELAY);
+        }
+        Ok(false)
+    }
+
+    fn skip(&mut self) -> Result<bool> {
+        // a failed grab is picked up (and recovered from) by the next read
+        Ok(!self.end_of_stream && self.cam.grab().unwrap_or(false))
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn timestamp_ms(&self) -> f64 {
+        self.timestamp_ms
+    }
+
+    fn fps(&self) -> f64 {
+        self.fps
+    }
+
+    fn size(&self) -> Size {
+        self.size
+    }
+
+    fn is_end_of_stream(&self) -> bool {
+        self.end_of_stream
+    }
+
+    fn is_live(&self) -> bool {
+        true
+    }
+}
@@ -0,0 +1,80 @@
+use opencv::core::{Mat, MatTraitConst, Size};
+use opencv::prelude::{VideoCaptureTrait, VideoCaptureTraitConst};
+use opencv::videoio::{self, VideoCapture};
+use crate::error::{Result, VisionError};
+use crate::sources::frame_source::{capture_size, FrameSource};
+
+/// A video file decoded through OpenCV's video I/O.
+pub struct FileSource {
+    cam: VideoCapture,
+    path: String,
+    size: Size,
+    fps: f64,
+    frame_count: usize,
+    end_of_stream: bool,
+}
+
+impl FileSource {
+    pub fn open(file_path: &str) -> Result<Self> {
+        let cam = VideoCapture::from_file(
+            file_path,
+            videoio::CAP_ANY
+        ).map_err(|e| VisionError::source_open(file_path, e))?;
+
+        // Check if video file opened successfully
+        if !cam.is_opened()? {
+            return Err(VisionError::source_open(file_path, "not a readable video file"));
+        }
+
+        Ok(Self {
+            size: capture_size(&cam)?,
+            fps: cam.get(videoio::CAP_PROP_FPS)?,
+            frame_count: cam.get(videoio::CAP_PROP_FRAME_COUNT)?.max(0.0) as usize,
+            cam,
+            path: file_path.to_string(),
+            end_of_stream: false,
+        })
+    }
+}
+
+impl FrameSource for FileSource {
+    fn read(&mut self, frame: &mut Mat) -> Result<bool> {
+        if self.end_of_stream {
+            return Ok(false);
+        }
+        self.end_of_stream = !self.cam.read(frame)? || frame.empty();
+        Ok(!self.end_of_stream)
+    }
+
+    fn skip(&mut self) -> Result<bool> {
+        if self.end_of_stream {
+            return Ok(false);
+        }
+        self.end_of_stream = !self.cam.grab()?;
+        Ok(!self.end_of_stream)
+    }
+
+    fn name(&self) -> &str {
+        &self.path
+    }
+
+    fn timestamp_ms(&self) -> f64 {
+        self.cam.get(videoio::CAP_PROP_POS_MSEC).unwrap_or(0.0)
+    }
+
+    fn fps(&self) -> f64 {
+        self.fps
+    }
+
+    fn size(&self) -> Size {
+        self.size
+    }
+
+    fn is_end_of_stream(&self) -> bool {
+        self.end_of_stream
+    }
+
+    fn frame_count(&self) -> Option<usize> {
+        Some(self.frame_count)
+    }
+}
@@ -0,0 +1,45 @@
+use opencv::core::{Mat, Size};
+use opencv::prelude::VideoCaptureTraitConst;
+use opencv::videoio::{self, VideoCapture};
+use crate::error::Result;
+
+/// Anything frames can be read from: files, cameras, network streams, images or memory.
+pub trait FrameSource {
+    /// Reads the next frame into `frame`, returning false once the source is exhausted.
+    fn read(&mut self, frame: &mut Mat) -> Result<bool>;
+
+    /// Advances past the next frame without needing its contents.
+    fn skip(&mut self) -> Result<bool> {
+        let mut frame = Mat::default();
+        self.read(&mut frame)
+    }
+
+    /// File path, device or URL the frames come from, used to label outputs.
+    fn name(&self) -> &str;
+
+    /// Media timestamp of the last frame read, in milliseconds.
+    fn timestamp_ms(&self) -> f64;
+
+    fn fps(&self) -> f64;
+
+    fn size(&self) -> Size;
+
+    fn is_end_of_stream(&self) -> bool;
+
+    /// Total number of frames, if the source knows it up front.
+    fn frame_count(&self) -> Option<usize> {
+        None
+    }
+
+    /// Live sources (cameras, streams) run until they fail rather than until they end.
+    fn is_live(&self) -> bool {
+        false
+    }
+}
+
+pub fn capture_size(cam: &VideoCapture) -> Result<Size> {
+    Ok(Size::new(
+        cam.get(videoio::CAP_PROP_FRAME_WIDTH)? as i32,
+        cam.get(videoio::CAP_PROP_FRAME_HEIGHT)? as i32,
+    ))
+}
@@ -0,0 +1,212 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use opencv::core::{Mat, MatTraitConst, Size};
+use opencv::imgcodecs;
+use crate::error::{Result, VisionError};
+use crate::sources::frame_source::FrameSource;
+
+const IMAGE_EXTENSIONS: [&str; 3] = ["jpg", "jpeg", "png"];
+
+/// Plays back an ordered set of still images as if they were video frames, either every
+/// JPEG/PNG in a directory (sorted by file name) or a printf-style pattern such as
+/// `frames/frame_%06d.png`.
+pub struct ImageSequence {
+    source: String,
+    paths: Vec<PathBuf>,
+    index: usize,
+    fps: f64,
+    size: Size,
+}
+
+impl ImageSequence {
+    pub fn open(source: &str, fps: f64) -> Result<Self> {
+        if fps <= 0.0 {
+            return Err(VisionError::InvalidConfig(
+                format!("image sequence frame rate must be positive, got {}", fps),
+            ));
+        }
+
+        let paths = if Path::new(source).is_dir() {
+            Self::list_dir(Path::new(source))?
+        } else {
+            Self::expand_pattern(source)?
+        };
+
+        if paths.is_empty() {
+            return Err(VisionError::source_open(source, "no JPEG or PNG images found"));
+        }
+
+        // every image is expected to match the size of the first one
+        let first = imgcodecs::imread(paths[0].to_str().unwrap_or_default(), imgcodecs::IMREAD_COLOR)?;
+        if first.empty() {
+            return Err(VisionError::Decode(format!("unreadable image {:?}", paths[0])));
+        }
+        let size = first.size()?;
+
+        Ok(Self {
+            source: source.to_string(),
+            paths,
+            index: 0,
+            fps,
+            size,
+        })
+    }
+
+    fn list_dir(dir: &Path) -> Result<Vec<PathBuf>> {
+        let entries = fs::read_dir(dir)
+            .map_err(|e| VisionError::source_open(dir.to_string_lossy(), e))?;
+
+        let mut paths: Vec<PathBuf> = entries
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|path| path.is_file())
+            .filter(|path| {
+                path.extension()
+                    .and_then(|ext| ext.to_str())
+                    .map(|ext| IMAGE_EXTENSIONS.contains(&ext.to_lowercase().as_str()))
+                    .unwrap_or(false)
+            })
+            .collect();
+        paths.sort();
+        Ok(paths)
+    }
+
+    // the sequence starts at index 0 (or 1, as many cameras count from one) and runs until
+    // the first missing file
+    fn expand_pattern(pattern: &str) -> Result<Vec<PathBuf>> {
+        let first = format_pattern(pattern, 0).ok_or_else(|| VisionError::source_open(
+            pattern,
+            "not a directory or printf-style pattern (e.g. frame_%06d.png)",
+        ))?;
+
+        let start = if first.is_file() { 0 } else { 1 };
+        let mut paths = Vec::new();
+        let mut index = start;
+        while let Some(path) = format_pattern(pattern, index) {
+            if !path.is_file() {
+                break;
+            }
+            paths.push(path);
+            index += 1;
+        }
+        Ok(paths)
+    }
+
+    pub fn len(&self) -> usize {
+        self.paths.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.paths.is_empty()
+    }
+
+    pub fn position(&self) -> usize {
+        self.index
+    }
+}
+
+impl FrameSource for ImageSequence {
+    fn read(&mut self, frame: &mut Mat) -> Result<bool> {
+        if self.index >= self.paths.len() {
+            return Ok(false);
+        }
+        let path = &self.paths[self.index];
+        *frame = imgcodecs::imread(path.to_str().unwrap_or_default(), imgcodecs::IMREAD_COLOR)?;
+        self.index += 1;
+        if frame.empty() {
+            return Err(VisionError::Decode(format!("unreadable image {:?}", path)));
+        }
+        Ok(true)
+    }
+
+    fn skip(&mut self) -> Result<bool> {
+        if self.index >= self.paths.len() {
+            return Ok(false);
+        }
+        self.index += 1;
+        Ok(true)
+    }
+
+    fn name(&self) -> &str {
+        &self.source
+    }
+
+    fn timestamp_ms(&self) -> f64 {
+        self.index.saturating_sub(1) as f64 * 1000.0 / self.fps
+    }
+
+    fn fps(&self) -> f64 {
+        self.fps
+    }
+
+    fn size(&self) -> Size {
+        self.size
+    }
+
+    fn is_end_of_stream(&self) -> bool {
+        self.index >= self.paths.len()
+    }
+
+    fn frame_count(&self) -> Option<usize> {
+        Some(self.paths.len())
+    }
+}
+
+// expands the last `%d` / `%0Nd` conversion in the pattern, so that a `%` in a directory name
+// is left alone, and `%%` to a literal `%`; None if there is no conversion
+pub fn format_pattern(pattern: &str, index: usize) -> Option<PathBuf> {
+    let mut text = String::with_capacity(pattern.len());
+    let mut conversion = None;          // (range in `text`, width spec) of the last conversion
+    let mut rest = pattern;
+    while let Some(start) = rest.find('%') {
+        text.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        if let Some(after) = after.strip_prefix('%') {
+            text.push('%');
+            rest = after;
+            continue;
+        }
+        let spec_len = after.find(|c: char| !c.is_ascii_digit()).unwrap_or(after.len());
+        if after[spec_len..].starts_with('d') {
+            let raw = &rest[start..start + spec_len + 2];
+            conversion = Some((text.len()..text.len() + raw.len(), &after[..spec_len]));
+            text.push_str(raw);
+            rest = &after[spec_len + 1..];
+        } else {
+            text.push('%');
+            rest = after;
+        }
+    }
+    text.push_str(rest);
+
+    let (range, spec) = conversion?;
+    let width = spec.parse::<usize>().unwrap_or(0);
+    let number = if spec.starts_with('0') {
+        format!("{:0width$}", index, width = width)
+    } else {
+        format!("{:width$}", index, width = width)
+    };
+    text.replace_range(range, &number);
+
+    Some(PathBuf::from(text))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pattern_expands_the_last_conversion() {
+        assert_eq!(format_pattern("img_%06d.png", 42), Some(PathBuf::from("img_000042.png")));
+        assert_eq!(format_pattern("img_%d.png", 7), Some(PathBuf::from("img_7.png")));
+        assert_eq!(format_pattern("frames/50%/img_%04d.png", 3), Some(PathBuf::from("frames/50%/img_0003.png")));
+        assert_eq!(format_pattern("run_%d/img_%03d.png", 5), Some(PathBuf::from("run_%d/img_005.png")));
+        assert_eq!(format_pattern("100%%_%d.png", 1), Some(PathBuf::from("100%_1.png")));
+    }
+
+    #[test]
+    fn pattern_without_a_conversion_is_rejected() {
+        assert_eq!(format_pattern("img.png", 0), None);
+        assert_eq!(format_pattern("frames/50%/img.png", 0), None);
+        assert_eq!(format_pattern("img_%%d.png", 0), None);
+    }
+}
@@ -0,0 +1,69 @@
+use opencv::core::{Mat, MatTraitConst, Size};
+use crate::error::Result;
+use crate::sources::frame_source::FrameSource;
+
+/// Plays back frames held in memory, e.g. generated by a test, without touching a codec.
+pub struct MemorySource {
+    frames: Vec<Mat>,
+    index: usize,
+    fps: f64,
+}
+
+impl MemorySource {
+    pub fn new(frames: Vec<Mat>, fps: f64) -> Self {
+        Self {
+            frames,
+            index: 0,
+            fps,
+        }
+    }
+
+    pub fn push(&mut self, frame: Mat) {
+        self.frames.push(frame);
+    }
+}
+
+impl FrameSource for MemorySource {
+    fn read(&mut self, frame: &mut Mat) -> Result<bool> {
+        if self.index >= self.frames.len() {
+            return Ok(false);
+        }
+        self.frames[self.index].copy_to(frame)?;
+        self.index += 1;
+        Ok(true)
+    }
+
+    fn skip(&mut self) -> Result<bool> {
+        if self.index >= self.frames.len() {
+            return Ok(false);
+        }
+        self.index += 1;
+        Ok(true)
+    }
+
+    fn name(&self) -> &str {
+        "memory"
+    }
+
+    fn timestamp_ms(&self) -> f64 {
+        self.index.saturating_sub(1) as f64 * 1000.0 / self.fps
+    }
+
+    fn fps(&self) -> f64 {
+        self.fps
+    }
+
+    fn size(&self) -> Size {
+        self.frames.first()
+            .and_then(|frame| frame.size().ok())
+            .unwrap_or_default()
+    }
+
+    fn is_end_of_stream(&self) -> bool {
+        self.index >= self.frames.len()
+    }
+
+    fn frame_count(&self) -> Option<usize> {
+        Some(self.frames.len())
+    }
+}
@@ -0,0 +1,16 @@
+//! Frame sources: everything that can feed frames into [`VideoFrames`](crate::util::video_frames::VideoFrames),
+//! from video files and cameras to in-memory and synthetic frames for tests.
+
+pub mod frame_source;
+
+pub mod file_source;
+
+pub mod device_source;
+
+pub mod stream_source;
+
+pub mod image_sequence;
+
+pub mod memory_source;
+
+pub mod synthetic_scene;
@@ -0,0 +1,155 @@
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+use opencv::core::{Mat, MatTraitConst, Size, Vector};
+use opencv::prelude::{VideoCaptureTrait, VideoCaptureTraitConst};
+use opencv::videoio::{self, VideoCapture};
+use crate::error::Result;
+use crate::sources::frame_source::{capture_size, FrameSource};
+use crate::util::stream_reconnect::{ReconnectPolicy, StreamStatus, StreamStatusCallback};
+
+// delay between read attempts while a stream has not yet been declared stalled
+const RETRY_READ_DELAY: Duration = Duration::from_millis(100);
+
+/// A network stream opened through FFmpeg, which reconnects when the stream fails or stalls.
+pub struct StreamSource {
+    cam: VideoCapture,
+    url: String,
+    policy: ReconnectPolicy,
+    stall_timeout: Duration,
+    status_callback: Option<StreamStatusCallback>,
+    size: Size,
+    fps: f64,
+    started_at: Instant,
+    last_frame_time: Instant,
+    timestamp_ms: f64,
+    end_of_stream: bool,
+}
+
+impl StreamSource {
+    pub fn new(url: &str, policy: ReconnectPolicy, stall_timeout: Duration) -> Result<Self> {
+        let now = Instant::now();
+        Ok(Self {
+            cam: VideoCapture::default()?,
+            url: url.to_string(),
+            policy,
+            stall_timeout,
+            status_callback: None,
+            size: Size::default(),
+            fps: 0.0,
+            started_at: now,
+            last_frame_time: now,
+            timestamp_ms: 0.0,
+            end_of_stream: false,
+        })
+    }
+
+    pub fn set_status_callback(&mut self, callback: Option<StreamStatusCallback>) {
+        self.status_callback = callback;
+    }
+
+    pub fn take_status_callback(&mut self) -> Option<StreamStatusCallback> {
+        self.status_callback.take()
+    }
+
+    fn notify_status(&mut self, status: StreamStatus) {
+        if let Some(callback) = self.status_callback.as_mut() {
+            callback(&status);
+        }
+    }
+
+    fn open(&mut self) -> Result<bool> {
+        // bound the time FFmpeg may block, otherwise a dead camera hangs the read forever
+        let timeout_ms = self.stall_timeout.as_millis().min(i32::MAX as u128) as i32;
+        let params = Vector::<i32>::from_slice(&[
+            videoio::CAP_PROP_OPEN_TIMEOUT_MSEC, timeout_ms,
+            videoio::CAP_PROP_READ_TIMEOUT_MSEC, timeout_ms,
+        ]);
+
+        match VideoCapture::from_file_with_params(&self.url, videoio::CAP_FFMPEG, &params) {
+            Ok(cam) => {
+                self.cam = cam;
+                Ok(self.cam.is_opened()?)
+            }
+            Err(_) => Ok(false),
+        }
+    }
+
+    // (re)opens the stream, backing off between failed attempts; returns false once the
+    // reconnect policy is exhausted
+    pub fn connect(&mut self) -> Result<bool> {
+        let mut attempt = 0;
+        loop {
+            attempt += 1;
+            self.notify_status(StreamStatus::Connecting { attempt });
+
+            if self.open()? {
+                self.size = capture_size(&self.cam)?;
+                self.fps = self.cam.get(videoio::CAP_PROP_FPS)?;
+                self.last_frame_time = Instant::now();
+                self.notify_status(StreamStatus::Connected);
+                return Ok(true);
+            }
+
+            if self.policy.is_exhausted(attempt) {
+                self.end_of_stream = true;
+                self.notify_status(StreamStatus::GaveUp { attempts: attempt });
+                return Ok(false);
+            }
+
+            let delay = self.policy.delay(attempt);
+            self.notify_status(StreamStatus::Reconnecting { attempt, delay });
+            sleep(delay);
+        }
+    }
+}
+
+impl FrameSource for StreamSource {
+    fn read(&mut self, frame: &mut Mat) -> Result<bool> {
+        while !self.end_of_stream {
+            if self.cam.read(frame).unwrap_or(false) && !frame.empty() {
+                self.last_frame_time = Instant::now();
+                self.timestamp_ms = self.started_at.elapsed().as_secs_f64() * 1000.0;
+                return Ok(true);
+            }
+
+            let since_last_frame = self.last_frame_time.elapsed();
+            if since_last_frame < self.stall_timeout {
+                sleep(RETRY_READ_DELAY);
+                continue;
+            }
+
+            self.notify_status(StreamStatus::Stalled { since_last_frame });
+            self.connect()?;
+        }
+        Ok(false)
+    }
+
+    fn skip(&mut self) -> Result<bool> {
+        // a failed grab is picked up (and recovered from) by the next read
+        Ok(!self.end_of_stream && self.cam.grab().unwrap_or(false))
+    }
+
+    fn name(&self) -> &str {
+        &self.url
+    }
+
+    fn timestamp_ms(&self) -> f64 {
+        self.timestamp_ms
+    }
+
+    fn fps(&self) -> f64 {
+        self.fps
+    }
+
+    fn size(&self) -> Size {
+        self.size
+    }
+
+    fn is_end_of_stream(&self) -> bool {
+        self.end_of_stream
+    }
+
+    fn is_live(&self) -> bool {
+        true
+    }
+}
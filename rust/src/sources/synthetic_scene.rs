@@ -0,0 +1,385 @@
+use opencv::core::{self, Mat, MatTraitConst, Point, Rect, Scalar, Size, RNG, RNGTrait};
+use opencv::imgproc;
+use crate::error::Result;
+use crate::sources::frame_source::FrameSource;
+
+// size of the random texture that is scaled up to form the background
+const TEXTURE_SCALE: i32 = 16;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Shape {
+    Rectangle,
+    Circle,
+    Shadow,                             // a rectangle darkening whatever is under it
+}
+
+/// A shape moving at constant velocity through a [`SyntheticScene`].
+#[derive(Debug, Clone)]
+pub struct SyntheticObject {
+    pub shape: Shape,
+    pub start: Point,                   // top-left corner at frame 0
+    pub size: Size,                     // circles use the width as their diameter
+    pub velocity: (f64, f64),           // pixels per frame
+    pub color: Scalar,                  // for shadows, the share of the brightness they keep
+}
+
+impl SyntheticObject {
+    pub fn rectangle(start: Point, size: Size, velocity: (f64, f64), color: Scalar) -> Self {
+        Self {
+            shape: Shape::Rectangle,
+            start,
+            size,
+            velocity,
+            color,
+        }
+    }
+
+    pub fn circle(start: Point, diameter: i32, velocity: (f64, f64), color: Scalar) -> Self {
+        Self {
+            shape: Shape::Circle,
+            start,
+            size: Size::new(diameter, diameter),
+            velocity,
+            color,
+        }
+    }
+
+    // a cast shadow keeping `brightness` (e.g. 0.6) of the brightness of what it falls on
+    pub fn shadow(start: Point, size: Size, velocity: (f64, f64), brightness: f64) -> Self {
+        Self {
+            shape: Shape::Shadow,
+            start,
+            size,
+            velocity,
+            color: Scalar::all(brightness),
+        }
+    }
+
+    // unclipped bounding box of the object at the given frame
+    pub fn bounds_at(&self, index: usize) -> Rect {
+        Rect::new(
+            self.start.x + (self.velocity.0 * index as f64).round() as i32,
+            self.start.y + (self.velocity.1 * index as f64).round() as i32,
+            self.size.width,
+            self.size.height,
+        )
+    }
+
+    fn draw(&self, frame: &mut Mat, bounds: Rect) -> Result<()> {
+        match self.shape {
+            Shape::Rectangle => imgproc::rectangle(
+                frame,
+                bounds,
+                self.color,
+                imgproc::FILLED,
+                imgproc::LINE_8,
+                0,
+            )?,
+            Shape::Circle => imgproc::circle(
+                frame,
+                Point::new(bounds.x + bounds.width / 2, bounds.y + bounds.width / 2),
+                bounds.width / 2,
+                self.color,
+                imgproc::FILLED,
+                imgproc::LINE_8,
+                0,
+            )?,
+            Shape::Shadow => {
+                let mut darkened = Mat::default();
+                frame.convert_to(&mut darkened, -1, self.color[0], 0.0)?;
+                let mut mask = Mat::new_size_with_default(frame.size()?, core::CV_8UC1, Scalar::all(0.0))?;
+                imgproc::rectangle(&mut mask, bounds, Scalar::all(255.0), imgproc::FILLED, imgproc::LINE_8, 0)?;
+                darkened.copy_to_masked(frame, &mask)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Generates a deterministic video of moving shapes over a textured background, together with
+/// the exact bounding box of every object in every frame. Each frame depends only on the seed
+/// and its index, so any frame can be rendered again in isolation.
+pub struct SyntheticScene {
+    size: Size,
+    fps: f64,
+    frame_count: usize,
+    seed: u64,
+    background: Mat,
+    objects: Vec<SyntheticObject>,
+    noise_sigma: f64,                   // standard deviation of per-pixel gaussian noise
+    lighting_ramp: f64,                 // brightness change per frame
+    jitter: i32,                        // maximum camera shake in pixels
+    index: usize,
+    ground_truth: Vec<Rect>,
+}
+
+impl SyntheticScene {
+    pub fn new(size: Size, fps: f64, frame_count: usize, seed: u64) -> Result<Self> {
+        let mut rng = RNG::new(seed)?;
+        let mut texture = Mat::new_size_with_default(
+            Size::new(
+                (size.width / TEXTURE_SCALE).max(1),
+                (size.height / TEXTURE_SCALE).max(1),
+            ),
+            core::CV_8UC3,
+            Scalar::default(),
+        )?;
+        rng.fill(
+            &mut texture,
+            core::RNG_UNIFORM,
+            &Scalar::all(60.0),
+            &Scalar::all(140.0),
+            false,
+        )?;
+
+        let mut background = Mat::default();
+        imgproc::resize(
+            &texture,
+            &mut background,
+            size,
+            0.0,
+            0.0,
+            imgproc::INTER_CUBIC,
+        )?;
+
+        Ok(Self {
+            size,
+            fps,
+            frame_count,
+            seed,
+            background,
+            objects: Vec::new(),
+            noise_sigma: 0.0,
+            lighting_ramp: 0.0,
+            jitter: 0,
+            index: 0,
+            ground_truth: Vec::new(),
+        })
+    }
+
+    // the scene the detector tests share: the background on its own for ~85 frames, then an
+    // object of the given shape, 160 pixels across, crossing it at 32 pixels per frame
+    #[cfg(test)]
+    pub fn fixture(shape: Option<Shape>) -> Self {
+        let scene = Self::new(Size::new(1280, 720), 25.0, 101, 11).unwrap();
+        let (start, size, velocity) = (Point::new(-2720, 280), Size::new(160, 160), (32.0, 0.0));
+        match shape {
+            Some(Shape::Rectangle) => scene.with_object(SyntheticObject::rectangle(start, size, velocity, Scalar::all(255.0))),
+            Some(Shape::Circle) => scene.with_object(SyntheticObject::circle(start, size.width, velocity, Scalar::all(255.0))),
+            Some(Shape::Shadow) => scene.with_object(SyntheticObject::shadow(start, size, velocity, 0.6)),
+            None => scene,
+        }
+    }
+
+    pub fn with_object(mut self, object: SyntheticObject) -> Self {
+        self.objects.push(object);
+        self
+    }
+
+    pub fn with_noise(mut self, sigma: f64) -> Self {
+        self.noise_sigma = sigma;
+        self
+    }
+
+    pub fn with_lighting_ramp(mut self, per_frame: f64) -> Self {
+        self.lighting_ramp = per_frame;
+        self
+    }
+
+    pub fn with_jitter(mut self, max_pixels: i32) -> Self {
+        self.jitter = max_pixels;
+        self
+    }
+
+    pub fn objects(&self) -> &Vec<SyntheticObject> {
+        &self.objects
+    }
+
+    // ground truth boxes (clipped to the frame) of the frame last returned by `read`
+    pub fn ground_truth(&self) -> &Vec<Rect> {
+        &self.ground_truth
+    }
+
+    pub fn render(&self, index: usize) -> Result<(Mat, Vec<Rect>)> {
+        let mut rng = RNG::new(self.seed.wrapping_add(1 + index as u64))?;
+        let mut frame = self.background.clone();
+
+        let mut boxes = Vec::with_capacity(self.objects.len());
+        for object in &self.objects {
+            let bounds = object.bounds_at(index);
+            object.draw(&mut frame, bounds)?;
+            boxes.push(bounds);
+        }
+
+        if self.lighting_ramp != 0.0 {
+            let mut lit = Mat::default();
+            frame.convert_to(&mut lit, -1, 1.0, self.lighting_ramp * index as f64)?;
+            frame = lit;
+        }
+
+        if self.jitter > 0 {
+            let dx = rng.uniform(-self.jitter, self.jitter + 1)?;
+            let dy = rng.uniform(-self.jitter, self.jitter + 1)?;
+            let translation = Mat::from_slice_2d(&[
+                [1.0f64, 0.0, dx as f64],
+                [0.0, 1.0, dy as f64],
+            ])?;
+            let mut shaken = Mat::default();
+            imgproc::warp_affine(
+                &frame,
+                &mut shaken,
+                &translation,
+                self.size,
+                imgproc::INTER_NEAREST,
+                core::BORDER_REFLECT,
+                Scalar::default(),
+            )?;
+            frame = shaken;
+            for bounds in boxes.iter_mut() {
+                bounds.x += dx;
+                bounds.y += dy;
+            }
+        }
+
+        if self.noise_sigma > 0.0 {
+            let mut noise = Mat::new_size_with_default(self.size, core::CV_16SC3, Scalar::default())?;
+            rng.fill(
+                &mut noise,
+                core::RNG_NORMAL,
+                &Scalar::all(0.0),
+                &Scalar::all(self.noise_sigma),
+                false,
+            )?;
+            let mut noisy = Mat::default();
+            core::add(&frame, &noise, &mut noisy, &core::no_array(), core::CV_8UC3)?;
+            frame = noisy;
+        }
+
+        let frame_rect = Rect::new(0, 0, self.size.width, self.size.height);
+        let boxes = boxes.into_iter()
+            .map(|bounds| bounds & frame_rect)
+            .filter(|bounds| !bounds.empty())
+            .collect();
+
+        Ok((frame, boxes))
+    }
+}
+
+impl FrameSource for SyntheticScene {
+    fn read(&mut self, frame: &mut Mat) -> Result<bool> {
+        if self.index >= self.frame_count {
+            return Ok(false);
+        }
+        let (rendered, boxes) = self.render(self.index)?;
+        *frame = rendered;
+        self.ground_truth = boxes;
+        self.index += 1;
+        Ok(true)
+    }
+
+    fn skip(&mut self) -> Result<bool> {
+        if self.index >= self.frame_count {
+            return Ok(false);
+        }
+        self.index += 1;
+        Ok(true)
+    }
+
+    fn name(&self) -> &str {
+        "synthetic"
+    }
+
+    fn timestamp_ms(&self) -> f64 {
+        self.index.saturating_sub(1) as f64 * 1000.0 / self.fps
+    }
+
+    fn fps(&self) -> f64 {
+        self.fps
+    }
+
+    fn size(&self) -> Size {
+        self.size
+    }
+
+    fn is_end_of_stream(&self) -> bool {
+        self.index >= self.frame_count
+    }
+
+    fn frame_count(&self) -> Option<usize> {
+        Some(self.frame_count)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use opencv::core::norm2;
+
+    fn scene() -> SyntheticScene {
+        SyntheticScene::new(Size::new(640, 360), 25.0, 20, 7)
+            .unwrap()
+            .with_object(SyntheticObject::rectangle(
+                Point::new(40, 100),
+                Size::new(80, 60),
+                (10.0, 0.0),
+                Scalar::all(255.0),
+            ))
+            .with_object(SyntheticObject::circle(
+                Point::new(500, 200),
+                50,
+                (-5.0, -5.0),
+                Scalar::new(0.0, 0.0, 255.0, 0.0),
+            ))
+    }
+
+    #[test]
+    fn same_seed_renders_identical_frames() {
+        let a = scene().with_noise(8.0).with_jitter(3);
+        let b = scene().with_noise(8.0).with_jitter(3);
+        for index in [0, 5, 19] {
+            let (frame_a, boxes_a) = a.render(index).unwrap();
+            let (frame_b, boxes_b) = b.render(index).unwrap();
+            assert_eq!(boxes_a, boxes_b);
+            assert_eq!(norm2(&frame_a, &frame_b, core::NORM_L1, &core::no_array()).unwrap(), 0.0);
+        }
+    }
+
+    #[test]
+    fn ground_truth_follows_objects() {
+        let (_, boxes) = scene().render(4).unwrap();
+        assert_eq!(boxes, vec![
+            Rect::new(80, 100, 80, 60),
+            Rect::new(480, 180, 50, 50),
+        ]);
+    }
+
+    #[test]
+    fn ground_truth_is_clipped_to_frame() {
+        let scene = SyntheticScene::new(Size::new(320, 240), 25.0, 10, 1)
+            .unwrap()
+            .with_object(SyntheticObject::rectangle(
+                Point::new(280, 10),
+                Size::new(60, 40),
+                (20.0, 0.0),
+                Scalar::all(255.0),
+            ));
+        let (_, boxes) = scene.render(0).unwrap();
+        assert_eq!(boxes, vec![Rect::new(280, 10, 40, 40)]);
+        let (_, boxes) = scene.render(3).unwrap();
+        assert!(boxes.is_empty());
+    }
+
+    #[test]
+    fn plays_back_as_frame_source() {
+        let mut scene = scene();
+        let mut frame = Mat::default();
+        let mut frames = 0;
+        while scene.read(&mut frame).unwrap() {
+            assert_eq!(frame.size().unwrap(), Size::new(640, 360));
+            assert_eq!(scene.ground_truth().len(), 2);
+            frames += 1;
+        }
+        assert_eq!(frames, 20);
+        assert!(scene.is_end_of_stream());
+    }
+}
@@ -0,0 +1,75 @@
+/// A one dimensional constant velocity Kalman filter, stepped once per processed frame.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ConstantVelocity {
+    position: f64,
+    velocity: f64,
+    covariance: [[f64; 2]; 2],
+    process_noise: f64,                 // variance of the acceleration
+    measurement_noise: f64,             // variance of the measured position
+}
+
+impl ConstantVelocity {
+    pub fn new(position: f64, process_noise: f64, measurement_noise: f64) -> Self {
+        Self {
+            position,
+            velocity: 0.0,
+            // the first measurement says nothing about the velocity
+            covariance: [[measurement_noise, 0.0], [0.0, measurement_noise * 100.0]],
+            process_noise,
+            measurement_noise,
+        }
+    }
+
+    pub fn position(&self) -> f64 {
+        self.position
+    }
+
+    pub fn velocity(&self) -> f64 {
+        self.velocity
+    }
+
+    pub fn predict(&mut self) {
+        let [[p00, p01], [p10, p11]] = self.covariance;
+        let q = self.process_noise;
+
+        self.position += self.velocity;
+        // F P F^T + Q, with F = [1 1; 0 1] and Q the discrete white noise acceleration model
+        self.covariance = [
+            [p00 + p01 + p10 + p11 + q / 4.0, p01 + p11 + q / 2.0],
+            [p10 + p11 + q / 2.0, p11 + q],
+        ];
+    }
+
+    pub fn correct(&mut self, measurement: f64) {
+        let [[p00, p01], [p10, p11]] = self.covariance;
+
+        let innovation = measurement - self.position;
+        let gain = [p00 / (p00 + self.measurement_noise), p10 / (p00 + self.measurement_noise)];
+
+        self.position += gain[0] * innovation;
+        self.velocity += gain[1] * innovation;
+        self.covariance = [
+            [(1.0 - gain[0]) * p00, (1.0 - gain[0]) * p01],
+            [p10 - gain[1] * p00, p11 - gain[1] * p01],
+        ];
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn learns_the_velocity_of_a_steady_motion() {
+        let mut filter = ConstantVelocity::new(0.0, 0.01, 4.0);
+        for step in 1..=30 {
+            filter.predict();
+            filter.correct(step as f64 * 3.0);
+        }
+        assert!((filter.velocity() - 3.0).abs() < 0.1, "{}", filter.velocity());
+
+        // without measurements the prediction carries on at that velocity
+        filter.predict();
+        assert!((filter.position() - 93.0).abs() < 1.0, "{}", filter.position());
+    }
+}
@@ -0,0 +1,114 @@
+use std::collections::{HashMap, HashSet};
+use serde::{Deserialize, Serialize};
+use crate::error::Result;
+use crate::masks::zones::Zones;
+use crate::tracking::tracker::Tracker;
+
+/// A track that has stayed inside a zone for longer than the zone's `loiter_ms`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Loitering {
+    pub zone: String,
+    pub track_id: u64,
+    pub duration_ms: f64,               // time spent inside the zone so far
+}
+
+/// Times how long each confirmed track stays inside each zone. A track is reported once per
+/// visit, and leaving the zone starts its dwell time over.
+pub struct Dwell {
+    entered_ms: HashMap<(u64, usize), f64>,     // (track, zone) => when the track entered the zone
+    reported: HashSet<(u64, usize)>,
+}
+
+impl Dwell {
+    pub fn new() -> Self {
+        Self {
+            entered_ms: HashMap::new(),
+            reported: HashSet::new(),
+        }
+    }
+
+    // how long a track has been inside a zone, if it is inside
+    pub fn dwell_ms(&self, track_id: u64, zone: usize, timestamp_ms: f64) -> Option<f64> {
+        self.entered_ms.get(&(track_id, zone)).map(|entered_ms| timestamp_ms - entered_ms)
+    }
+
+    // checked every processed frame, so that a track standing still keeps accruing time
+    pub fn update(&mut self, tracker: &Tracker, zones: &Zones, timestamp_ms: f64) -> Result<Vec<Loitering>> {
+        let mut loitering = Vec::new();
+        let mut inside = HashSet::new();
+        for track in tracker.confirmed() {
+            for (index, zone) in zones.zones().iter().enumerate() {
+                if zone.loiter_ms() == 0 || !zone.contains(track.centroid())? {
+                    continue;
+                }
+                let key = (track.id(), index);
+                inside.insert(key);
+                let duration_ms = timestamp_ms - *self.entered_ms.entry(key).or_insert(timestamp_ms);
+                if duration_ms >= zone.loiter_ms() as f64 && self.reported.insert(key) {
+                    loitering.push(Loitering {
+                        zone: zone.name().to_string(),
+                        track_id: track.id(),
+                        duration_ms,
+                    });
+                }
+            }
+        }
+
+        // tracks that left a zone, or are gone altogether, start over
+        self.entered_ms.retain(|key, _| inside.contains(key));
+        self.reported.retain(|key| inside.contains(key));
+        Ok(loitering)
+    }
+}
+
+impl Default for Dwell {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use opencv::core::{Rect, Size};
+    use crate::config::{MotionEventSettings, TrackerSettings, ZoneKind, ZoneSettings};
+    use crate::util::video_frames::VideoFrames;
+
+    #[test]
+    fn reports_tracks_staying_too_long_once_per_visit() {
+        let video_frames = VideoFrames::new(Size::new(1280, 720), Size::new(640, 360), Size::new(320, 180)).unwrap();
+        // the left half of the frame, loitering after a second
+        let settings = vec![ZoneSettings {
+            name: String::from("porch"),
+            kind: ZoneKind::Include,
+            points: vec![[0.0, 0.0], [640.0, 0.0], [640.0, 720.0], [0.0, 720.0]],
+            loiter_ms: 1000,
+            ..ZoneSettings::default()
+        }];
+        let zones = Zones::new(&settings, &MotionEventSettings::default(), &video_frames).unwrap();
+        let mut tracker = Tracker::new(&TrackerSettings::default());
+        let mut dwell = Dwell::new();
+
+        // a box at quarter resolution, standing in the zone for a while and then walking out of it
+        let xs: Vec<i32> = [40; 15].into_iter().chain((50..=200).step_by(10)).collect();
+        let mut reported = Vec::new();
+        for (step, x) in xs.iter().enumerate() {
+            let timestamp_ms = step as f64 * 100.0;
+            tracker.update(&[Rect::new(*x, 80, 20, 20)], timestamp_ms);
+            for loitering in dwell.update(&tracker, &zones, timestamp_ms).unwrap() {
+                reported.push((step, loitering));
+            }
+        }
+
+        // confirmed at step 2 inside the zone, so loitering from step 12 on
+        assert_eq!(reported.len(), 1);
+        let (step, loitering) = &reported[0];
+        assert_eq!(*step, 12);
+        assert_eq!(loitering.zone, "porch");
+        assert_eq!(loitering.track_id, 1);
+        assert_eq!(loitering.duration_ms, 1000.0);
+
+        // once outside, the dwell time is gone
+        assert!(dwell.dwell_ms(1, 0, xs.len() as f64 * 100.0).is_none());
+    }
+}
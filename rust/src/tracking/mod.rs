@@ -0,0 +1,10 @@
+//! Follows motion blobs across frames so that each object keeps one ID, counts them across lines
+//! and times how long they stay in zones.
+
+pub mod kalman;
+
+pub mod loitering;
+
+pub mod tracker;
+
+pub mod tripwire;
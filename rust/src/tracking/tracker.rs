@@ -0,0 +1,320 @@
+use std::collections::VecDeque;
+use opencv::core::{Point2d, Rect};
+use crate::config::TrackerSettings;
+use crate::tracking::kalman::ConstantVelocity;
+
+/// An object followed across frames. Its box is the Kalman estimate, in the coordinates of the
+/// detections the tracker is fed.
+#[derive(Debug, Clone)]
+pub struct Track {
+    id: u64,
+    filters: [ConstantVelocity; 4],     // centre x, centre y, width, height
+    detection: Option<Rect>,            // the detection matched in the latest update
+    hits: u32,
+    missed: u32,                        // consecutive updates without a matching detection
+    confirmed: bool,
+    history: VecDeque<Point2d>,         // centres of the matched detections, oldest first
+    first_seen_ms: f64,
+    last_seen_ms: f64,
+}
+
+impl Track {
+    fn new(id: u64, detection: Rect, timestamp_ms: f64, settings: &TrackerSettings) -> Self {
+        let [x, y, width, height] = measurement(detection);
+        let filter = |value| ConstantVelocity::new(value, settings.process_noise, settings.measurement_noise);
+        let mut track = Self {
+            id,
+            filters: [filter(x), filter(y), filter(width), filter(height)],
+            detection: Some(detection),
+            hits: 1,
+            missed: 0,
+            confirmed: settings.min_hits <= 1,
+            history: VecDeque::with_capacity(settings.history),
+            first_seen_ms: timestamp_ms,
+            last_seen_ms: timestamp_ms,
+        };
+        track.record(detection, settings.history);
+        track
+    }
+
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    pub fn bounding_box(&self) -> Rect {
+        let [x, y, width, height] = self.filters.map(|filter| filter.position());
+        let (width, height) = (width.max(1.0), height.max(1.0));
+        Rect::new(
+            (x - width / 2.0).round() as i32,
+            (y - height / 2.0).round() as i32,
+            width.round() as i32,
+            height.round() as i32,
+        )
+    }
+
+    pub fn centroid(&self) -> Point2d {
+        Point2d::new(self.filters[0].position(), self.filters[1].position())
+    }
+
+    // in pixels per processed frame
+    pub fn velocity(&self) -> Point2d {
+        Point2d::new(self.filters[0].velocity(), self.filters[1].velocity())
+    }
+
+    pub fn detection(&self) -> Option<Rect> {
+        self.detection
+    }
+
+    pub fn is_confirmed(&self) -> bool {
+        self.confirmed
+    }
+
+    pub fn hits(&self) -> u32 {
+        self.hits
+    }
+
+    pub fn missed(&self) -> u32 {
+        self.missed
+    }
+
+    pub fn history(&self) -> &VecDeque<Point2d> {
+        &self.history
+    }
+
+    pub fn first_seen_ms(&self) -> f64 {
+        self.first_seen_ms
+    }
+
+    pub fn last_seen_ms(&self) -> f64 {
+        self.last_seen_ms
+    }
+
+    fn predict(&mut self) {
+        for filter in self.filters.iter_mut() {
+            filter.predict();
+        }
+    }
+
+    fn correct(&mut self, detection: Rect, timestamp_ms: f64, settings: &TrackerSettings) {
+        for (filter, value) in self.filters.iter_mut().zip(measurement(detection)) {
+            filter.correct(value);
+        }
+        self.detection = Some(detection);
+        self.hits += 1;
+        self.missed = 0;
+        self.confirmed |= self.hits >= settings.min_hits;
+        self.last_seen_ms = timestamp_ms;
+        self.record(detection, settings.history);
+    }
+
+    fn miss(&mut self) {
+        self.detection = None;
+        self.missed += 1;
+    }
+
+    fn record(&mut self, detection: Rect, length: usize) {
+        if self.history.len() == length {
+            self.history.pop_front();
+        }
+        let [x, y, _, _] = measurement(detection);
+        self.history.push_back(Point2d::new(x, y));
+    }
+}
+
+/// Follows motion blobs across frames and gives each a stable ID. Detections are matched to
+/// the predicted boxes of the tracks by overlap first and by distance between centres second.
+/// An unmatched detection starts a tentative track, which is confirmed after `min_hits` matches
+/// and dropped on its first miss; confirmed tracks coast on their prediction for up to
+/// `max_missed` updates.
+pub struct Tracker {
+    settings: TrackerSettings,
+    tracks: Vec<Track>,
+    next_id: u64,
+}
+
+impl Tracker {
+    pub fn new(settings: &TrackerSettings) -> Self {
+        Self {
+            settings: settings.clone(),
+            tracks: Vec::new(),
+            next_id: 1,
+        }
+    }
+
+    // every live track, tentative ones included
+    pub fn tracks(&self) -> &Vec<Track> {
+        &self.tracks
+    }
+
+    pub fn confirmed(&self) -> impl Iterator<Item = &Track> {
+        self.tracks.iter().filter(|track| track.confirmed)
+    }
+
+    // the confirmed track that a detection of the latest update was matched to
+    pub fn track_of(&self, detection: &Rect) -> Option<&Track> {
+        self.confirmed().find(|track| track.detection.as_ref() == Some(detection))
+    }
+
+    pub fn update(&mut self, detections: &[Rect], timestamp_ms: f64) {
+        for track in self.tracks.iter_mut() {
+            track.predict();
+        }
+
+        let mut matched_tracks = vec![false; self.tracks.len()];
+        let mut matched_detections = vec![false; detections.len()];
+        for (track, detection) in self.associate(detections) {
+            self.tracks[track].correct(detections[detection], timestamp_ms, &self.settings);
+            matched_tracks[track] = true;
+            matched_detections[detection] = true;
+        }
+
+        for (track, matched) in self.tracks.iter_mut().zip(&matched_tracks) {
+            if !matched {
+                track.miss();
+            }
+        }
+        let max_missed = self.settings.max_missed;
+        self.tracks.retain(|track| track.missed == 0 || (track.confirmed && track.missed <= max_missed));
+
+        for (detection, matched) in detections.iter().zip(matched_detections) {
+            if !matched {
+                self.tracks.push(Track::new(self.next_id, *detection, timestamp_ms, &self.settings));
+                self.next_id += 1;
+            }
+        }
+    }
+
+    // greedy matching, best overlap first, then the closest centres among what is left
+    fn associate(&self, detections: &[Rect]) -> Vec<(usize, usize)> {
+        let mut by_overlap = Vec::new();
+        let mut by_distance = Vec::new();
+        for (t, track) in self.tracks.iter().enumerate() {
+            let predicted = track.bounding_box();
+            for (d, detection) in detections.iter().enumerate() {
+                let overlap = iou(&predicted, detection);
+                if overlap >= self.settings.iou_threshold {
+                    by_overlap.push((overlap, t, d));
+                }
+                let distance = centre_distance(&predicted, detection);
+                if distance <= self.settings.max_distance {
+                    by_distance.push((-distance, t, d));
+                }
+            }
+        }
+
+        let mut used_tracks = vec![false; self.tracks.len()];
+        let mut used_detections = vec![false; detections.len()];
+        let mut pairs = Vec::new();
+        for mut candidates in [by_overlap, by_distance] {
+            candidates.sort_by(|a, b| b.0.total_cmp(&a.0));
+            for (_, t, d) in candidates {
+                if !used_tracks[t] && !used_detections[d] {
+                    used_tracks[t] = true;
+                    used_detections[d] = true;
+                    pairs.push((t, d));
+                }
+            }
+        }
+        pairs
+    }
+}
+
+fn measurement(rect: Rect) -> [f64; 4] {
+    [
+        rect.x as f64 + rect.width as f64 / 2.0,
+        rect.y as f64 + rect.height as f64 / 2.0,
+        rect.width as f64,
+        rect.height as f64,
+    ]
+}
+
+pub fn iou(a: &Rect, b: &Rect) -> f64 {
+    let width = (a.x + a.width).min(b.x + b.width) - a.x.max(b.x);
+    let height = (a.y + a.height).min(b.y + b.height) - a.y.max(b.y);
+    if width <= 0 || height <= 0 {
+        return 0.0;
+    }
+    let intersection = width as f64 * height as f64;
+    let union = a.width as f64 * a.height as f64 + b.width as f64 * b.height as f64 - intersection;
+    intersection / union
+}
+
+fn centre_distance(a: &Rect, b: &Rect) -> f64 {
+    let [ax, ay, _, _] = measurement(*a);
+    let [bx, by, _, _] = measurement(*b);
+    (ax - bx).hypot(ay - by)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings() -> TrackerSettings {
+        TrackerSettings {
+            min_hits: 3,
+            max_missed: 2,
+            history: 4,
+            ..TrackerSettings::default()
+        }
+    }
+
+    // one object moving right along the top, one moving left along the bottom
+    fn detections(step: i32) -> Vec<Rect> {
+        vec![
+            Rect::new(10 + step * 8, 10, 40, 30),
+            Rect::new(250 - step * 8, 120, 30, 40),
+        ]
+    }
+
+    #[test]
+    fn objects_keep_their_ids() {
+        let mut tracker = Tracker::new(&settings());
+        for step in 0..10 {
+            tracker.update(&detections(step), step as f64 * 100.0);
+            let confirmed: Vec<u64> = tracker.confirmed().map(|track| track.id()).collect();
+            assert_eq!(confirmed, if step < 2 { vec![] } else { vec![1, 2] }, "step {}", step);
+        }
+
+        let current = detections(9);
+        assert_eq!(tracker.track_of(&current[0]).unwrap().id(), 1);
+        assert_eq!(tracker.track_of(&current[1]).unwrap().id(), 2);
+
+        let track = &tracker.tracks()[0];
+        assert!((track.velocity().x - 8.0).abs() < 1.0, "{:?}", track.velocity());
+        assert_eq!(track.history().len(), 4);
+        assert_eq!(track.first_seen_ms(), 0.0);
+        assert_eq!(track.last_seen_ms(), 900.0);
+    }
+
+    #[test]
+    fn confirmed_tracks_coast_and_tentative_tracks_die() {
+        let mut tracker = Tracker::new(&settings());
+        for step in 0..4 {
+            tracker.update(&detections(step)[..1], step as f64 * 100.0);
+        }
+        // a tentative track appears once and is gone on the next update
+        tracker.update(&[detections(4)[0], Rect::new(300, 300, 20, 20)], 400.0);
+        assert_eq!(tracker.tracks().len(), 2);
+
+        // the confirmed track survives two missed updates and picks up where it was predicted
+        tracker.update(&[], 500.0);
+        assert_eq!(tracker.tracks().len(), 1);
+        tracker.update(&[], 600.0);
+        tracker.update(&detections(7)[..1], 700.0);
+        assert_eq!(tracker.tracks()[0].id(), 1);
+        assert_eq!(tracker.tracks()[0].missed(), 0);
+
+        for step in 8..11 {
+            tracker.update(&[], step as f64 * 100.0);
+        }
+        assert!(tracker.tracks().is_empty());
+    }
+
+    #[test]
+    fn overlap_of_boxes() {
+        let a = Rect::new(0, 0, 10, 10);
+        assert_eq!(iou(&a, &a), 1.0);
+        assert_eq!(iou(&a, &Rect::new(5, 0, 10, 10)), 50.0 / 150.0);
+        assert_eq!(iou(&a, &Rect::new(10, 0, 10, 10)), 0.0);
+    }
+}
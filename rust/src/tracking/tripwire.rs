@@ -0,0 +1,211 @@
+use std::collections::HashSet;
+use opencv::core::{Point2d, Size};
+use serde::{Deserialize, Serialize};
+use crate::config::TripwireSettings;
+use crate::tracking::tracker::{Track, Tracker};
+
+/// Side A of a line is on the left when looking from its start towards its end, side B on
+/// the right.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CrossingDirection {
+    AToB,
+    BToA,
+}
+
+/// A track crossing a line, with the line's counts including this crossing.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Crossing {
+    pub line: String,
+    pub track_id: u64,
+    pub direction: CrossingDirection,
+    pub a_to_b: u64,
+    pub b_to_a: u64,
+}
+
+/// A counting line, held in the coordinates of the detections.
+#[derive(Debug, Clone)]
+pub struct Tripwire {
+    name: String,
+    start: Point2d,
+    end: Point2d,
+    a_to_b: u64,
+    b_to_a: u64,
+    counted: HashSet<(u64, CrossingDirection)>,     // each track counts once per direction
+}
+
+impl Tripwire {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn start(&self) -> Point2d {
+        self.start
+    }
+
+    pub fn end(&self) -> Point2d {
+        self.end
+    }
+
+    pub fn a_to_b(&self) -> u64 {
+        self.a_to_b
+    }
+
+    pub fn b_to_a(&self) -> u64 {
+        self.b_to_a
+    }
+
+    // which way, if any, the step from one centre to the next crosses the line
+    fn crossed(&self, from: Point2d, to: Point2d) -> Option<CrossingDirection> {
+        let side_from = cross(self.start, self.end, from);
+        let side_to = cross(self.start, self.end, to);
+        let direction = if side_from < 0.0 && side_to >= 0.0 {
+            CrossingDirection::AToB
+        } else if side_from > 0.0 && side_to <= 0.0 {
+            CrossingDirection::BToA
+        } else {
+            return None;
+        };
+
+        // the step has to pass between the ends of the line, not beside it
+        let start_side = cross(from, to, self.start);
+        let end_side = cross(from, to, self.end);
+        if start_side * end_side <= 0.0 { Some(direction) } else { None }
+    }
+
+    fn count(&mut self, track: &Track, from: Point2d, to: Point2d) -> Option<Crossing> {
+        let direction = self.crossed(from, to)?;
+        if !self.counted.insert((track.id(), direction)) {
+            return None;
+        }
+        match direction {
+            CrossingDirection::AToB => self.a_to_b += 1,
+            CrossingDirection::BToA => self.b_to_a += 1,
+        }
+        Some(Crossing {
+            line: self.name.clone(),
+            track_id: track.id(),
+            direction,
+            a_to_b: self.a_to_b,
+            b_to_a: self.b_to_a,
+        })
+    }
+}
+
+/// Counts confirmed tracks crossing lines, per line and direction. Lines are configured in
+/// full resolution pixels and scaled to the resolution the tracker works at.
+pub struct Tripwires {
+    lines: Vec<Tripwire>,
+    confirmed: HashSet<u64>,            // tracks whose history has been checked already
+}
+
+impl Tripwires {
+    pub fn new(settings: &[TripwireSettings], full_size: Size, detector_size: Size) -> Self {
+        let scale_x = detector_size.width as f64 / full_size.width.max(1) as f64;
+        let scale_y = detector_size.height as f64 / full_size.height.max(1) as f64;
+        let point = |[x, y]: [f64; 2]| Point2d::new(x * scale_x, y * scale_y);
+
+        Self {
+            lines: settings.iter()
+                .map(|line| Tripwire {
+                    name: line.name.clone(),
+                    start: point(line.start),
+                    end: point(line.end),
+                    a_to_b: 0,
+                    b_to_a: 0,
+                    counted: HashSet::new(),
+                })
+                .collect(),
+            confirmed: HashSet::new(),
+        }
+    }
+
+    pub fn lines(&self) -> &Vec<Tripwire> {
+        &self.lines
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lines.is_empty()
+    }
+
+    // checks the latest step of every confirmed track that was matched in the last update
+    pub fn update(&mut self, tracker: &Tracker) -> Vec<Crossing> {
+        let mut crossings = Vec::new();
+        if self.lines.is_empty() {
+            return crossings;
+        }
+
+        for track in tracker.confirmed().filter(|track| track.missed() == 0) {
+            // a track that was just confirmed may have crossed while it was still tentative
+            let history = track.history();
+            let steps = if self.confirmed.insert(track.id()) { history.len().saturating_sub(1) } else { 1 };
+            for index in history.len().saturating_sub(steps + 1)..history.len().saturating_sub(1) {
+                for line in self.lines.iter_mut() {
+                    crossings.extend(line.count(track, history[index], history[index + 1]));
+                }
+            }
+        }
+
+        let live: HashSet<u64> = tracker.tracks().iter().map(|track| track.id()).collect();
+        self.confirmed.retain(|id| live.contains(id));
+        crossings
+    }
+}
+
+// positive when the point lies to the right of the line from start to end, as seen on screen
+fn cross(start: Point2d, end: Point2d, point: Point2d) -> f64 {
+    (end.x - start.x) * (point.y - start.y) - (end.y - start.y) * (point.x - start.x)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use opencv::core::Rect;
+    use crate::config::TrackerSettings;
+
+    fn door() -> Vec<TripwireSettings> {
+        // a horizontal line across the middle of a 1280x720 frame, side A above it
+        vec![TripwireSettings {
+            name: String::from("door"),
+            start: [200.0, 360.0],
+            end: [1080.0, 360.0],
+        }]
+    }
+
+    // feeds the tracker one 20x20 box per step, centred on the given point at quarter resolution
+    fn follow(tripwires: &mut Tripwires, centres: &[(i32, i32)]) -> Vec<Crossing> {
+        let mut tracker = Tracker::new(&TrackerSettings::default());
+        let mut crossings = Vec::new();
+        for (step, (x, y)) in centres.iter().enumerate() {
+            tracker.update(&[Rect::new(x - 10, y - 10, 20, 20)], step as f64 * 100.0);
+            crossings.extend(tripwires.update(&tracker));
+        }
+        crossings
+    }
+
+    #[test]
+    fn counts_each_direction_once_per_track() {
+        let mut tripwires = Tripwires::new(&door(), Size::new(1280, 720), Size::new(320, 180));
+        assert_eq!(tripwires.lines()[0].start(), Point2d::new(50.0, 90.0));
+
+        // down across the line while still tentative, then dithering back and forth across it
+        let centres: Vec<(i32, i32)> = [70, 80, 92, 100, 88, 96, 86, 95, 104].iter().map(|y| (160, *y)).collect();
+        let crossings = follow(&mut tripwires, &centres);
+
+        let directions: Vec<CrossingDirection> = crossings.iter().map(|crossing| crossing.direction).collect();
+        assert_eq!(directions, vec![CrossingDirection::AToB, CrossingDirection::BToA]);
+        assert_eq!(crossings[0].line, "door");
+        assert_eq!(crossings[0].track_id, 1);
+        assert_eq!((tripwires.lines()[0].a_to_b(), tripwires.lines()[0].b_to_a()), (1, 1));
+    }
+
+    #[test]
+    fn ignores_steps_beside_or_along_the_line() {
+        let mut tripwires = Tripwires::new(&door(), Size::new(1280, 720), Size::new(320, 180));
+        // crossing y = 90 to the left of where the line starts, then moving along it
+        let beside: Vec<(i32, i32)> = [70, 80, 90, 100, 110].iter().map(|y| (30, *y)).collect();
+        let along: Vec<(i32, i32)> = [60, 80, 100, 120, 140].iter().map(|x| (*x, 85)).collect();
+        assert!(follow(&mut tripwires, &beside).is_empty());
+        assert!(follow(&mut tripwires, &along).is_empty());
+    }
+}
@@ -0,0 +1,251 @@
+use std::io;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+/// The parts of an `http://host:port/path` URL needed to send a request to it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Url {
+    pub host: String,
+    pub port: u16,
+    pub path: String,
+}
+
+impl Url {
+    // plain HTTP only, which is all a model served on the local machine or network needs
+    pub fn parse(url: &str) -> io::Result<Self> {
+        let invalid = |reason: &str| io::Error::new(io::ErrorKind::InvalidInput, format!("{}: {}", url, reason));
+        let rest = url.strip_prefix("http://").ok_or_else(|| invalid("only http:// URLs are supported"))?;
+        let (authority, path) = match rest.find('/') {
+            Some(index) => (&rest[..index], &rest[index..]),
+            None => (rest, "/"),
+        };
+        // IPv6 literals are bracketed so that their colons are not taken for the port's
+        let (host, port) = match authority.strip_prefix('[') {
+            Some(bracketed) => {
+                let (host, port) = bracketed.split_once(']').ok_or_else(|| invalid("the IPv6 address is not closed"))?;
+                match port {
+                    "" => (host, None),
+                    _ => (host, Some(port.strip_prefix(':').ok_or_else(|| invalid("the port is not a number"))?)),
+                }
+            }
+            None => match authority.rsplit_once(':') {
+                Some((host, port)) => (host, Some(port)),
+                None => (authority, None),
+            },
+        };
+        let port = match port {
+            Some(port) => port.parse().map_err(|_| invalid("the port is not a number"))?,
+            None => 80,
+        };
+        if host.is_empty() {
+            return Err(invalid("the host is missing"));
+        }
+        Ok(Self {
+            host: host.to_string(),
+            port,
+            path: path.to_string(),
+        })
+    }
+
+    // `host:port`, with an IPv6 host back in its brackets
+    pub fn authority(&self) -> String {
+        if self.host.contains(':') {
+            format!("[{}]:{}", self.host, self.port)
+        } else {
+            format!("{}:{}", self.host, self.port)
+        }
+    }
+}
+
+/// The status code and body of an HTTP response.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Response {
+    pub status: u16,
+    pub body: String,
+}
+
+// POSTs a JSON body on a connection of its own and reads the whole response, or gives up
+// once the timeout has passed
+pub async fn post_json(url: &Url, headers: &[(String, String)], body: &str, timeout: Duration) -> io::Result<Response> {
+    match tokio::time::timeout(timeout, exchange(url, headers, body)).await {
+        Ok(response) => response,
+        Err(_) => Err(io::Error::new(
+            io::ErrorKind::TimedOut,
+            format!("no response from {} within {} ms", url.authority(), timeout.as_millis()),
+        )),
+    }
+}
+
+async fn exchange(url: &Url, headers: &[(String, String)], body: &str) -> io::Result<Response> {
+    let mut stream = TcpStream::connect((url.host.as_str(), url.port)).await?;
+
+    let mut request = format!(
+        "POST {} HTTP/1.1\r\nHost: {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n",
+        url.path,
+        url.authority(),
+        body.len(),
+    );
+    for (name, value) in headers {
+        request.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    request.push_str("\r\n");
+    request.push_str(body);
+    stream.write_all(request.as_bytes()).await?;
+
+    // the server closes the connection once it has answered
+    let mut response = Vec::new();
+    stream.read_to_end(&mut response).await?;
+    parse_response(&response)
+}
+
+fn parse_response(bytes: &[u8]) -> io::Result<Response> {
+    let header_end = find(bytes, b"\r\n\r\n").ok_or_else(|| invalid_data("the response header is incomplete"))?;
+    let header = String::from_utf8_lossy(&bytes[..header_end]);
+    let mut body = &bytes[header_end + 4..];
+
+    let mut lines = header.split("\r\n");
+    let status = lines.next()
+        .and_then(|line| line.split_whitespace().nth(1))
+        .and_then(|code| code.parse().ok())
+        .ok_or_else(|| invalid_data("the status line is malformed"))?;
+
+    let mut chunked = false;
+    for (name, value) in lines.filter_map(|line| line.split_once(':')) {
+        let value = value.trim();
+        if name.eq_ignore_ascii_case("transfer-encoding") {
+            chunked = value.eq_ignore_ascii_case("chunked");
+        } else if name.eq_ignore_ascii_case("content-length") {
+            let length: usize = value.parse().map_err(|_| invalid_data("the content length is malformed"))?;
+            body = &body[..length.min(body.len())];
+        }
+    }
+
+    let body = if chunked { dechunk(body)? } else { body.to_vec() };
+    Ok(Response {
+        status,
+        body: String::from_utf8_lossy(&body).into_owned(),
+    })
+}
+
+// joins the chunks of a chunked transfer encoding, ignoring any extensions and trailers
+fn dechunk(mut bytes: &[u8]) -> io::Result<Vec<u8>> {
+    let mut body = Vec::new();
+    loop {
+        let line_end = find(bytes, b"\r\n").ok_or_else(|| invalid_data("a chunk size is incomplete"))?;
+        let line = String::from_utf8_lossy(&bytes[..line_end]);
+        let size = usize::from_str_radix(line.split(';').next().unwrap_or_default().trim(), 16)
+            .map_err(|_| invalid_data("a chunk size is malformed"))?;
+        bytes = &bytes[line_end + 2..];
+        if size == 0 {
+            return Ok(body);
+        }
+        if bytes.len() < size {
+            return Err(invalid_data("a chunk is truncated"));
+        }
+        body.extend_from_slice(&bytes[..size]);
+        bytes = bytes.get(size + 2..).unwrap_or_default();
+    }
+}
+
+fn find(bytes: &[u8], pattern: &[u8]) -> Option<usize> {
+    bytes.windows(pattern.len()).position(|window| window == pattern)
+}
+
+fn invalid_data(reason: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, reason.to_string())
+}
+
+// answers one request with a canned response, handing back the request it received
+#[cfg(test)]
+pub(crate) async fn serve_once(path: &str, response: String) -> (String, tokio::task::JoinHandle<String>) {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}{}", listener.local_addr().unwrap(), path);
+    let server = tokio::spawn(async move {
+        let (mut stream, _) = listener.accept().await.unwrap();
+        let mut request = Vec::new();
+        let mut buffer = [0u8; 4096];
+        // reads up to the end of the body that the header announces
+        loop {
+            let read = stream.read(&mut buffer).await.unwrap();
+            request.extend_from_slice(&buffer[..read]);
+            if let Some(header_end) = find(&request, b"\r\n\r\n") {
+                let header = String::from_utf8_lossy(&request[..header_end]).to_ascii_lowercase();
+                let length: usize = header.lines()
+                    .find_map(|line| line.strip_prefix("content-length:"))
+                    .map(|length| length.trim().parse().unwrap())
+                    .unwrap_or(0);
+                if request.len() >= header_end + 4 + length || read == 0 {
+                    break;
+                }
+            }
+        }
+        stream.write_all(response.as_bytes()).await.unwrap();
+        String::from_utf8_lossy(&request).into_owned()
+    });
+    (url, server)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn urls_are_split_into_host_port_and_path() {
+        let url = Url::parse("http://127.0.0.1:11434/api/chat").unwrap();
+        assert_eq!(url, Url { host: String::from("127.0.0.1"), port: 11434, path: String::from("/api/chat") });
+        assert_eq!(Url::parse("http://localhost").unwrap().port, 80);
+        assert_eq!(Url::parse("http://localhost").unwrap().path, "/");
+        assert!(Url::parse("https://api.example.com/v1").is_err());
+        assert!(Url::parse("http://:8080/").is_err());
+    }
+
+    #[test]
+    fn ipv6_hosts_lose_their_brackets() {
+        let url = Url::parse("http://[::1]:11434/api/chat").unwrap();
+        assert_eq!(url, Url { host: String::from("::1"), port: 11434, path: String::from("/api/chat") });
+        assert_eq!(url.authority(), "[::1]:11434");
+        assert_eq!(Url::parse("http://[fe80::2]/").unwrap().port, 80);
+        assert!(Url::parse("http://[::1/").is_err());
+        assert!(Url::parse("http://[::1]8080/").is_err());
+        assert!(Url::parse("http://[]:8080/").is_err());
+    }
+
+    #[tokio::test]
+    async fn posts_json_and_reads_the_response() {
+        let response = String::from("HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: 11\r\n\r\n{\"ok\":true}");
+        let (url, server) = serve_once("/v1/chat/completions", response).await;
+        let headers = vec![(String::from("Authorization"), String::from("Bearer secret"))];
+
+        let response = post_json(&Url::parse(&url).unwrap(), &headers, "{\"model\":\"m\"}", Duration::from_secs(5)).await.unwrap();
+        assert_eq!(response, Response { status: 200, body: String::from("{\"ok\":true}") });
+
+        let request = server.await.unwrap();
+        assert!(request.starts_with("POST /v1/chat/completions HTTP/1.1\r\n"), "{}", request);
+        assert!(request.contains("Authorization: Bearer secret\r\n"), "{}", request);
+        assert!(request.ends_with("\r\n\r\n{\"model\":\"m\"}"), "{}", request);
+    }
+
+    #[tokio::test]
+    async fn reads_chunked_responses() {
+        let response = String::from("HTTP/1.1 500 Internal Server Error\r\nTransfer-Encoding: chunked\r\n\r\n4\r\nfail\r\n3;x=y\r\ned!\r\n0\r\n\r\n");
+        let (url, _server) = serve_once("/", response).await;
+        let response = post_json(&Url::parse(&url).unwrap(), &[], "{}", Duration::from_secs(5)).await.unwrap();
+        assert_eq!(response, Response { status: 500, body: String::from("failed!") });
+    }
+
+    #[tokio::test]
+    async fn gives_up_after_the_timeout() {
+        // a server that accepts the connection but never answers
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = Url::parse(&format!("http://{}/", listener.local_addr().unwrap())).unwrap();
+        let _server = tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            tokio::time::sleep(Duration::from_secs(10)).await;
+            drop(stream);
+        });
+
+        let error = post_json(&url, &[], "{}", Duration::from_millis(100)).await.unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::TimedOut);
+    }
+}
@@ -1,8 +1,11 @@
-#[allow(dead_code)]
+//! Frame handling, timing and the processing loop that ties sources, detectors and overlays together.
+
 pub mod stop_watch;
 
-#[allow(dead_code)]
+pub mod http;
+
+pub mod stream_reconnect;
+
 pub mod video_frames;
 
-#[allow(dead_code)]
-pub mod video_processor;
\ No newline at end of file
+pub mod video_processor;
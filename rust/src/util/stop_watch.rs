@@ -1,9 +1,12 @@
 use std::collections::HashMap;
+use std::fmt;
 use std::time::{Duration, Instant};
 
+/// Times the stages of each processed frame as labelled laps and summarises them.
 pub struct StopWatch {
     begin: Instant,
     laps: Vec<(Duration, String)>,
+    frame_start: usize,                 // index of the first lap since start()
     end: Instant,
     total_ticks: i32,
     last_lap_time: Instant,
@@ -16,6 +19,7 @@ impl StopWatch {
         Self {
             begin: now,
             laps: Vec::new(),
+            frame_start: 0,
             end: now,
             total_ticks: 0,
             last_lap_time: now,
@@ -26,6 +30,7 @@ impl StopWatch {
         let now = Instant::now();
         self.begin = now;
         self.last_lap_time = now;
+        self.frame_start = self.laps.len();
     }
 
     pub fn lap(&mut self, label: &str) {
@@ -35,6 +40,11 @@ impl StopWatch {
         self.laps.push((duration, label.to_string()));
     }
 
+    // the laps of the frame being timed, i.e. since the last start()
+    pub fn frame_laps(&self) -> &[(Duration, String)] {
+        &self.laps[self.frame_start..]
+    }
+
     pub fn tick(&mut self) {
         self.total_ticks += 1;
         self.end = Instant::now();
@@ -44,6 +54,14 @@ impl StopWatch {
         self.end = Instant::now();
     }
 
+    pub fn elapsed(&self) -> Duration {
+        self.end.saturating_duration_since(self.begin)
+    }
+
+    pub fn total_ticks(&self) -> i32 {
+        self.total_ticks
+    }
+
     pub fn calc_stats(&self) -> Option<(Duration, Duration, Duration)> {
         if self.laps.is_empty() {
             return None;
@@ -56,9 +74,9 @@ impl StopWatch {
         for lap in &self.laps {
             min_duration = min_duration.min(lap.0);
             max_duration = max_duration.max(lap.0);
-            avg_duration = avg_duration + lap.0;
+            avg_duration += lap.0;
         }
-        avg_duration = avg_duration / self.laps.len() as u32;
+        avg_duration /= self.laps.len() as u32;
 
         Some((
             min_duration,
@@ -73,7 +91,7 @@ impl StopWatch {
         let mut lap_groups: HashMap<String, Vec<Duration>> = HashMap::new();
         for (duration, label) in &self.laps {
             lap_groups.entry(label.clone())
-                .or_insert_with(Vec::new)
+                .or_default()
                 .push(*duration);
         }
 
@@ -91,22 +109,6 @@ impl StopWatch {
         (total_stats, label_stats)
     }
 
-    pub fn to_string(&self) -> String {
-        let stats = self.calc_stats().unwrap();
-        let outstr = format!("\
-            Min: {} | {:.2} fps\n\
-            Max: {} | {:.2} fps\n\
-            Avg: {} | {:.2} fps\n",
-            StopWatch::format_duration_ms(stats.0),
-            1.0 / stats.0.as_secs_f32(),
-            StopWatch::format_duration_ms(stats.1),
-            1.0 / stats.1.as_secs_f32(),
-            StopWatch::format_duration_ms(stats.2),
-            1.0 / stats.2.as_secs_f32(),
-        );
-        outstr
-    }
-
     pub fn format_duration_ms(duration: Duration) -> String {
         let ns = duration.as_nanos();
         if ns < 1_000 {  // less than 1 microsecond
@@ -140,10 +142,36 @@ impl StopWatch {
                 outstr.push_str(&format!("  Min: {} | {:.2} fps\n", StopWatch::format_duration_ms(min), 1.0 / min.as_secs_f32()));
                 outstr.push_str(&format!("  Max: {} | {:.2} fps\n", StopWatch::format_duration_ms(max), 1.0 / min.as_secs_f32()));
                 outstr.push_str(&format!("  Avg: {} | {:.2} fps\n", StopWatch::format_duration_ms(avg), 1.0 / min.as_secs_f32()));
-                outstr.push_str("\n");
+                outstr.push('\n');
             }
         }
 
         outstr
     }
-}
\ No newline at end of file
+}
+
+impl Default for StopWatch {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Display for StopWatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let stats = match self.calc_stats() {
+            Some(stats) => stats,
+            None => return writeln!(f, "No frames processed"),
+        };
+        write!(f, "\
+            Min: {} | {:.2} fps\n\
+            Max: {} | {:.2} fps\n\
+            Avg: {} | {:.2} fps\n",
+            StopWatch::format_duration_ms(stats.0),
+            1.0 / stats.0.as_secs_f32(),
+            StopWatch::format_duration_ms(stats.1),
+            1.0 / stats.1.as_secs_f32(),
+            StopWatch::format_duration_ms(stats.2),
+            1.0 / stats.2.as_secs_f32(),
+        )
+    }
+}
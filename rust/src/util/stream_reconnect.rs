@@ -0,0 +1,102 @@
+use std::fmt;
+use std::time::Duration;
+
+/// Exponential backoff used between attempts to (re)open a network stream.
+pub struct ReconnectPolicy {
+    pub initial_delay: Duration,
+    pub max_delay: Duration,
+    pub multiplier: f64,
+    pub max_attempts: Option<u32>,      // None => keep trying forever
+}
+
+impl ReconnectPolicy {
+    pub fn new(initial_delay: Duration, max_delay: Duration, max_attempts: Option<u32>) -> Self {
+        Self {
+            initial_delay,
+            max_delay,
+            multiplier: 2.0,
+            max_attempts,
+        }
+    }
+
+    // delay to wait after the given (1-based) failed attempt
+    pub fn delay(&self, attempt: u32) -> Duration {
+        // clamped so that a long outage cannot wrap the exponent around to a negative one
+        let exponent = attempt.saturating_sub(1).min(i32::MAX as u32) as i32;
+        let delay = self.initial_delay.as_secs_f64() * self.multiplier.powi(exponent);
+        Duration::from_secs_f64(delay.min(self.max_delay.as_secs_f64()))
+    }
+
+    pub fn is_exhausted(&self, attempt: u32) -> bool {
+        match self.max_attempts {
+            Some(max_attempts) => attempt >= max_attempts,
+            None => false,
+        }
+    }
+}
+
+impl Default for ReconnectPolicy {
+    fn default() -> Self {
+        Self::new(
+            Duration::from_millis(500),
+            Duration::from_secs(30),
+            None,
+        )
+    }
+}
+
+/// Connection state changes reported by a stream source through its status callback.
+#[derive(Debug, Clone, PartialEq)]
+pub enum StreamStatus {
+    Connecting { attempt: u32 },
+    Connected,
+    Stalled { since_last_frame: Duration },
+    Reconnecting { attempt: u32, delay: Duration },
+    GaveUp { attempts: u32 },
+}
+
+impl fmt::Display for StreamStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StreamStatus::Connecting { attempt } => write!(f, "connecting (attempt {})", attempt),
+            StreamStatus::Connected => write!(f, "connected"),
+            StreamStatus::Stalled { since_last_frame } => {
+                write!(f, "stalled (no frames for {:.1}s)", since_last_frame.as_secs_f64())
+            }
+            StreamStatus::Reconnecting { attempt, delay } => {
+                write!(f, "attempt {} failed, retrying in {:.1}s", attempt, delay.as_secs_f64())
+            }
+            StreamStatus::GaveUp { attempts } => write!(f, "gave up after {} attempts", attempts),
+        }
+    }
+}
+
+pub type StreamStatusCallback = Box<dyn FnMut(&StreamStatus)>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn delay_doubles_up_to_the_cap() {
+        let policy = ReconnectPolicy::new(Duration::from_millis(500), Duration::from_secs(3), None);
+        let delays: Vec<u128> = (1..=5).map(|attempt| policy.delay(attempt).as_millis()).collect();
+        assert_eq!(delays, vec![500, 1000, 2000, 3000, 3000]);
+        assert_eq!(policy.delay(0), Duration::from_millis(500));
+        assert_eq!(policy.delay(u32::MAX), Duration::from_secs(3));
+    }
+
+    #[test]
+    fn exhausted_after_the_last_attempt() {
+        let policy = ReconnectPolicy::new(Duration::from_millis(500), Duration::from_secs(3), Some(3));
+        assert!(!policy.is_exhausted(2));
+        assert!(policy.is_exhausted(3));
+        assert!(!ReconnectPolicy::default().is_exhausted(u32::MAX));
+    }
+
+    #[test]
+    fn status_reads_as_a_sentence() {
+        let status = StreamStatus::Reconnecting { attempt: 2, delay: Duration::from_millis(1500) };
+        assert_eq!(status.to_string(), "attempt 2 failed, retrying in 1.5s");
+    }
+}
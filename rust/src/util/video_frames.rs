@@ -1,39 +1,53 @@
-use opencv::core::{Mat, MatTraitConst, Scalar, Size};
-use opencv::{imgproc, videoio, Error};
+use opencv::core::{Mat, MatTraitConst, Rect, Scalar, Size};
+use opencv::imgproc;
 use opencv::core::AlgorithmHint::ALGO_HINT_DEFAULT;
-use opencv::prelude::VideoCaptureTrait;
+use serde::{Deserialize, Serialize};
+use crate::error::{Result, VisionError};
+use crate::sources::frame_source::FrameSource;
 
+/// A detector stage, updated with the latest frames.
 pub trait FrameProcessor <'a> {
     fn update(
         &mut self,
         video_frames: &VideoFrames,
-    ) -> Result<(), Error>;
+    ) -> Result<()>;
 }
 
+/// A rung of the resolution ladder.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Tier {
+    Full,
+    Half,
+    #[default]
+    Quarter,
+}
+
+/// The current and previous frame at one resolution.
 pub struct Frame {
     pub cur: Mat,
     pub prev: Mat,
 }
 
 impl Frame {
-    pub fn new(size: Size, mat_type: i32) -> Self {
-        Self {
+    pub fn new(size: Size, mat_type: i32) -> Result<Self> {
+        Ok(Self {
             cur: Mat::new_size_with_default(
                 size,
                 mat_type,
                 Scalar::default()
-            ).unwrap(),
+            )?,
             prev: Mat::new_size_with_default(
                 size,
                 mat_type,
                 Scalar::default()
-            ).unwrap(),
-        }
+            )?,
+        })
     }
     pub fn invalidate(&mut self) {
         std::mem::swap(&mut self.prev, &mut self.cur);
     }
-    pub fn reset(&mut self) -> Result<(), Error> {
+    pub fn reset(&mut self) -> Result<()> {
         self.cur = Mat::new_size_with_default(
             self.cur.size()?,
             self.cur.typ(),
@@ -48,6 +62,7 @@ impl Frame {
     }
 }
 
+/// A frame at full, half and quarter resolution.
 pub struct FrameSamples {
     pub full: Frame,
     pub half: Frame,
@@ -60,11 +75,18 @@ impl FrameSamples {
         size_half: Size,
         size_quarter: Size,
         mat_type: i32,
-    ) -> Self {
-        Self {
-            full: Frame::new(size_full, mat_type),
-            half: Frame::new(size_half, mat_type),
-            quarter: Frame::new(size_quarter, mat_type),
+    ) -> Result<Self> {
+        Ok(Self {
+            full: Frame::new(size_full, mat_type)?,
+            half: Frame::new(size_half, mat_type)?,
+            quarter: Frame::new(size_quarter, mat_type)?,
+        })
+    }
+    pub fn tier(&self, tier: Tier) -> &Frame {
+        match tier {
+            Tier::Full => &self.full,
+            Tier::Half => &self.half,
+            Tier::Quarter => &self.quarter,
         }
     }
     pub fn invalidate(&mut self) {
@@ -72,7 +94,7 @@ impl FrameSamples {
         self.half.invalidate();
         self.quarter.invalidate();
     }
-    pub fn reset(&mut self) -> Result<(), Error> {
+    pub fn reset(&mut self) -> Result<()> {
         self.full.reset()?;
         self.half.reset()?;
         self.quarter.reset()?;
@@ -80,6 +102,7 @@ impl FrameSamples {
     }
 }
 
+/// Colour and (blurred) monochrome samples of the current and previous frame.
 pub struct VideoFrames {
     pub color: FrameSamples,
     pub mono: FrameSamples,
@@ -87,6 +110,7 @@ pub struct VideoFrames {
     size_full: Size,
     size_half: Size,
     size_quarter: Size,
+    blur_kernel: Size,
 }
 
 impl VideoFrames {
@@ -94,50 +118,100 @@ impl VideoFrames {
         size_full: Size,
         size_half: Size,
         size_quarter: Size,
-    ) -> Self {
-        Self {
+    ) -> Result<Self> {
+        Ok(Self {
             color: FrameSamples::new(
                 size_full,
                 size_half,
                 size_quarter,
                 opencv::core::CV_8UC3,
-            ),
+            )?,
             mono: FrameSamples::new(
                 size_full,
                 size_half,
                 size_quarter,
                 opencv::core::CV_8UC1,
-            ),
+            )?,
             size_full,
             size_half,
             size_quarter,
+            blur_kernel: Size::new(5, 5),
+        })
+    }
+
+    pub fn with_blur_kernel(mut self, blur_kernel: Size) -> Self {
+        self.blur_kernel = blur_kernel;
+        self
+    }
+
+    pub fn size_full(&self) -> Size {
+        self.size_full
+    }
+
+    pub fn size_half(&self) -> Size {
+        self.size_half
+    }
+
+    pub fn size_quarter(&self) -> Size {
+        self.size_quarter
+    }
+
+    pub fn size(&self, tier: Tier) -> Size {
+        match tier {
+            Tier::Full => self.size_full,
+            Tier::Half => self.size_half,
+            Tier::Quarter => self.size_quarter,
         }
     }
 
+    // maps a rectangle found in the quarter resolution samples onto the full resolution frame
+    pub fn quarter_to_full(&self, rect: Rect) -> Rect {
+        self.rescale(rect, Tier::Quarter, Tier::Full)
+    }
+
+    // maps a rectangle from the samples of one tier onto those of another
+    pub fn rescale(&self, rect: Rect, from: Tier, to: Tier) -> Rect {
+        let (from, to) = (self.size(from), self.size(to));
+        let scale_x = to.width as f64 / from.width as f64;
+        let scale_y = to.height as f64 / from.height as f64;
+        Rect::new(
+            (rect.x as f64 * scale_x).round() as i32,
+            (rect.y as f64 * scale_y).round() as i32,
+            (rect.width as f64 * scale_x).round() as i32,
+            (rect.height as f64 * scale_y).round() as i32,
+        )
+    }
+
     pub fn invalidate(&mut self) {
         self.color.invalidate();
         self.mono.invalidate();
     }
 
-    pub fn reset(&mut self) -> Result<(), Error> {
+    pub fn reset(&mut self) -> Result<()> {
         self.color.reset()?;
         self.mono.reset()?;
         Ok(())
     }
 
-    pub fn read_frame(&mut self, cam: &mut videoio::VideoCapture) -> opencv::Result<(), Error> {
+    // reads the next frame from the source, returning false once it is exhausted
+    pub fn read_frame(&mut self, source: &mut dyn FrameSource) -> Result<bool> {
+        let mut frame = Mat::default();
+        if !source.read(&mut frame)? {
+            return Ok(false);
+        }
+        self.load_frame(frame)?;
+        Ok(true)
+    }
 
-        cam.read(&mut self.color.full.cur)?;
+    pub fn load_frame(&mut self, frame: Mat) -> Result<()> {
 
-        if self.color.full.cur.empty() {
-            return Err(Error::new(
-                opencv::core::StsError,
-                String::from("No frames left in video."),
-            ));
+        if frame.empty() {
+            return Err(VisionError::Decode(String::from("source returned an empty frame")));
         }
 
-        // invalidate all frames
+        // invalidate all frames, then the new frame becomes the current full resolution frame
         self.invalidate();
+        self.color.full.cur = frame;
 
         // COLOR \\
         imgproc::resize(
@@ -188,7 +262,7 @@ impl VideoFrames {
         imgproc::gaussian_blur(
             &self.mono.half.cur,
             &mut blur_mono_half,
-            Size::new(5, 5),
+            self.blur_kernel,
             0.0,
             0.0,
             opencv::core::BORDER_DEFAULT,
@@ -200,7 +274,7 @@ impl VideoFrames {
         imgproc::gaussian_blur(
             &self.mono.quarter.cur,
             &mut blur_mono_quarter,
-            Size::new(5, 5),
+            self.blur_kernel,
             0.0,
             0.0,
             opencv::core::BORDER_DEFAULT,
@@ -1,97 +1,138 @@
+use std::collections::BTreeMap;
 use std::fs;
+use std::io;
 use std::path::Path;
-use std::process::exit;
-use opencv::core::Size;
+use std::time::Duration;
+use opencv::core::{Mat, Size};
+use opencv::highgui;
 use opencv::highgui::imshow;
-use opencv::{highgui, videoio};
-use opencv::hub_prelude::{VideoCaptureTrait, VideoCaptureTraitConst};
-use opencv::videoio::VideoCapture;
-use structopt::StructOpt;
 
-use crate::detectors::mean_squared_error::MeanSquaredError;
-use crate::detectors::motion_mog2::MotionMog2;
+use crate::config::{size, OutputSettings, Settings};
+use crate::detectors::cascade::{Cascade, Stage};
+use crate::detectors::motion_events::{MotionEvent, MotionEvents};
+use crate::error::{Result, VisionError};
 use crate::masks::motion_overlay::MotionOverlay;
 use crate::masks::overlay::OverlayProcessor;
+use crate::masks::tripwire_overlay::TripwireOverlay;
+use crate::masks::zones::{ZoneEvent, Zones};
+use crate::roi::classifier::Classifier;
+use crate::roi::vlm::{Description, VlmClient};
+use crate::sinks::clip_recorder::ClipRecorder;
+use crate::sinks::event_log::{BoundingBox, EventLog, FrameRecord};
+use crate::sinks::metrics_log::{FrameMetrics, MetricsLog};
+use crate::sinks::video_output::VideoOutput;
+use crate::sources::device_source::DeviceSource;
+use crate::sources::file_source::FileSource;
+use crate::sources::frame_source::FrameSource;
+use crate::sources::image_sequence::ImageSequence;
+use crate::sources::stream_source::StreamSource;
+use crate::tracking::loitering::Dwell;
+use crate::tracking::tracker::Tracker;
+use crate::tracking::tripwire::Tripwires;
 use crate::util::stop_watch::StopWatch;
-use crate::util::video_frames::{FrameProcessor, VideoFrames};
-
-
-#[derive(StructOpt, Debug)]
-#[structopt(name = "RustyVision", about = "OpenCV-based motion detection")]
-pub struct VideoConfig {
-
-    #[structopt(long, parse(from_os_str))]
-    pub input: std::path::PathBuf,
-
-    #[structopt(long)]
-    pub silent: bool,
-
-    #[structopt(long)]
-    pub verbose: bool,
-
-    #[structopt(long)]
-    pub headless: bool,
-
-    #[structopt(long, default_value = "2.0")]
-    pub target_fps: f64,
-
-    #[structopt(long, default_value = "0.4")]
-    pub mse_threshold: f64,
-
-    #[structopt(long, default_value = "5000.0")]
-    pub mog2_threshold: f64,
-
-    #[structopt(long, default_value = "500")]
-    pub mog2_history: i32,
-
-    #[structopt(long, default_value = "50.0")]
-    pub mog2_sensitivity: f64,
-
-    #[structopt(long, default_value = "17")]
-    pub adaptive_block_size: i32,
-
-    #[structopt(long, default_value = "9")]
-    pub adaptive_c: f64,
-}
+use crate::util::stream_reconnect::{ReconnectPolicy, StreamStatusCallback};
+use crate::util::video_frames::VideoFrames;
 
+/// Reads frames from a source and runs the motion cascade of the settings over them.
 pub struct VideoProcessor {
-    cam: VideoCapture,
+    settings: Settings,
     video_frames: VideoFrames,
-    mse_detector: MeanSquaredError,
-    mog2_detector: MotionMog2,
+    cascade: Cascade,
+    motion_events: MotionEvents,
+    tracker: Tracker,
+    tripwires: Tripwires,
+    zones: Zones,
+    dwell: Dwell,
+    classifier: Option<Classifier>,
+    vlm: Option<VlmClient>,                 // shared by every source, answers may arrive late
     stopwatch: StopWatch,
     video_fps: f64,
     frame_skip: i32,
     frame_counter: i32,
     read_frame_retry_count: i32,
-    motion_detected: bool,
+    status_callback: Option<StreamStatusCallback>,
+    video_output: Option<VideoOutput>,
+    clip_recorder: Option<ClipRecorder>,    // recreated for every source
+    event_log: Option<EventLog>,
+    metrics_log: Option<MetricsLog>,
+    motion_detected: bool,                  // a motion event is going on
+    stop_requested: bool,                   // ESC was pressed, skip any remaining sources
 }
 
 impl VideoProcessor {
 
-    pub fn new() -> Self {
-        Self {
-            cam: VideoCapture::default().unwrap(),
-            video_frames: VideoFrames::new(
-                Size::new(3840, 2160),
-                Size::new(1280, 720),
-                Size::new(640, 360),
-            ),
-            mse_detector: MeanSquaredError::default(),
-            mog2_detector: MotionMog2::default(),
+    pub fn new(settings: Settings) -> Result<Self> {
+        settings.validate()?;
+        // placeholders, the frames, detectors and zones are recreated for each source
+        let video_frames = VideoFrames::new(
+            size(settings.frames.half_resolution),
+            size(settings.frames.half_resolution),
+            size(settings.frames.quarter_resolution),
+        )?;
+        let cascade = Cascade::new(&settings, &video_frames)?;
+        let event_log = if settings.events.path.is_empty() {
+            None
+        } else {
+            Some(EventLog::open(&settings.events.path)?)
+        };
+        let metrics_log = if settings.metrics.path.is_empty() {
+            None
+        } else {
+            let columns: Vec<String> = cascade.stages().iter().map(Stage::column).collect();
+            Some(MetricsLog::open(&settings.metrics.path, &columns)?)
+        };
+        let classifier = if settings.classifier.model.is_empty() {
+            None
+        } else {
+            Some(Classifier::new(&settings.classifier)?)
+        };
+        let vlm = if settings.vlm.url.is_empty() {
+            None
+        } else {
+            Some(VlmClient::new(&settings.vlm)?)
+        };
+        Ok(Self {
+            zones: Zones::new(&[], &settings.motion, &video_frames)?,
+            video_frames,
+            dwell: Dwell::new(),
+            classifier,
+            vlm,
+            motion_events: MotionEvents::new(cascade.threshold(), &settings.motion),
+            cascade,
+            tracker: Tracker::new(&settings.tracker),
+            tripwires: Tripwires::new(&[], Size::default(), Size::default()),
+            settings,
             stopwatch: StopWatch::new(),
             video_fps: 0.0,
             frame_skip: 0,
             frame_counter: 0,
             read_frame_retry_count: 0,
+            status_callback: None,
+            video_output: None,
+            clip_recorder: None,
+            event_log,
+            metrics_log,
             motion_detected: false,
-        }
+            stop_requested: false,
+        })
+    }
+
+    pub fn settings(&self) -> &Settings {
+        &self.settings
     }
 
-    pub fn load_videos(&mut self, file_path: &Path, conf: &VideoConfig) -> opencv::Result<()> {
+    pub fn set_status_callback(&mut self, callback: StreamStatusCallback) {
+        self.status_callback = Some(callback);
+    }
+
+    pub fn load_videos(&mut self, file_path: &Path) -> Result<()> {
         if file_path.is_dir() {
-            for entry in fs::read_dir(&file_path).unwrap() {
-                let entry = entry.unwrap();
+            let entries = fs::read_dir(file_path)
+                .map_err(|e| VisionError::source_open(file_path.to_string_lossy(), e))?;
+            for entry in entries.flatten() {
+                if self.stop_requested {
+                    break;
+                }
                 let path = entry.path();
 
                 if path.is_dir() {
@@ -105,10 +146,11 @@ impl VideoProcessor {
                         extension == "mkv" ||
                         extension == "webm"
                     {
-                        if !conf.silent {
+                        if !self.settings.output.silent {
                             println!("Processing file: {:?}", path);
                         }
-                        if let Err(e) = self.process_video(path.to_str().unwrap(), conf) {
+                        // a bad file is reported and skipped, the rest of the batch carries on
+                        if let Err(e) = self.process_video(&path.to_string_lossy()) {
                             eprintln!("Failed to process file {:?}: {}", path, e);
                         }
                     }
@@ -116,51 +158,175 @@ impl VideoProcessor {
             }
             Ok(())
         } else {
-            self.process_video(file_path.to_str().unwrap(), conf)
+            self.process_video(&file_path.to_string_lossy())
         }
     }
 
-    pub fn process_video(&mut self, file_path: &str, conf: &VideoConfig) -> opencv::Result<()> {
+    pub fn process_video(&mut self, file_path: &str) -> Result<()> {
+        let mut source = FileSource::open(file_path)?;
+        self.process_source(&mut source)
+    }
 
-        self.cam = videoio::VideoCapture::from_file(
-            file_path,
-            videoio::CAP_ANY
-        )?;
+    pub fn process_device(&mut self, device: &str) -> Result<()> {
+        let mut source = DeviceSource::open(device, self.settings.source.max_dropped_frames)?;
+        let result = self.process_source(&mut source);
+        if source.is_end_of_stream() && !self.settings.output.silent {
+            println!("Lost video device after {} dropped frames", source.dropped_frame_count());
+        }
+        result
+    }
+
+    pub fn process_stream(&mut self, url: &str) -> Result<()> {
+        let conf = &self.settings.source;
+        let policy = ReconnectPolicy::new(
+            Duration::from_millis(conf.reconnect_delay_ms),
+            Duration::from_millis(conf.reconnect_max_delay_ms),
+            if conf.reconnect_attempts == 0 { None } else { Some(conf.reconnect_attempts) },
+        );
+        let mut source = StreamSource::new(url, policy, Duration::from_millis(conf.stall_timeout_ms))?;
+
+        // the callback is lent to the source for as long as it is being processed
+        source.set_status_callback(self.status_callback.take());
+        let result = match source.connect() {
+            Ok(true) => self.process_source(&mut source),
+            Ok(false) => Err(VisionError::source_open(url, "gave up reconnecting")),
+            Err(e) => Err(e),
+        };
+        self.status_callback = source.take_status_callback();
+        result
+    }
+
+    pub fn process_images(&mut self, images: &str) -> Result<()> {
+        let mut source = ImageSequence::open(images, self.settings.source.images_fps)?;
+        self.process_source(&mut source)
+    }
 
-        // Check if video file opened successfully
-        if !self.cam.is_opened()? {
-            panic!("Unable to open video file: {}", file_path);
+    // finalises the output video and the logs, which are shared by every source processed
+    pub fn finish(&mut self) -> Result<()> {
+        let descriptions = self.vlm.as_mut().map(|vlm| vlm.finish()).unwrap_or_default();
+        log_descriptions(descriptions, &self.settings.output, self.event_log.as_mut())?;
+        if let Some(event_log) = self.event_log.as_mut() {
+            event_log.finish()?;
         }
+        if let Some(metrics_log) = self.metrics_log.as_mut() {
+            metrics_log.finish()?;
+        }
+        if let Some(mut video_output) = self.video_output.take() {
+            video_output.release()?;
+            if !self.settings.output.silent {
+                println!("Wrote {} frames to {}", video_output.frame_count(), video_output.path());
+            }
+        }
+        Ok(())
+    }
+
+    fn print_stats(&self) {
+        if !self.settings.output.silent {
+            if self.settings.output.verbose {
+                println!("{}", self.stopwatch.to_string_detailed());
+            } else {
+                println!("{}", self.stopwatch.to_string());
+            }
+        }
+    }
+
+    pub fn process_source(&mut self, source: &mut dyn FrameSource) -> Result<()> {
+
+        let conf = &self.settings;
 
         // initialize
         self.video_frames = VideoFrames::new(
-            Size::new(
-                self.cam.get(videoio::CAP_PROP_FRAME_WIDTH)? as i32,
-                self.cam.get(videoio::CAP_PROP_FRAME_HEIGHT)? as i32
-            ),
-            Size::new(1280, 720),
-            Size::new(640, 360),
-        );
-        self.video_fps = self.cam.get(videoio::CAP_PROP_FPS)?;
-        self.frame_skip = (self.video_fps / conf.target_fps).ceil() as i32;
+            source.size(),
+            size(conf.frames.half_resolution),
+            size(conf.frames.quarter_resolution),
+        )?.with_blur_kernel(size(conf.frames.blur_kernel));
+        self.video_fps = source.fps();
+        // some devices do not report a frame rate, in which case every frame is processed
+        self.frame_skip = ((self.video_fps / conf.frames.target_fps).ceil() as i32).max(1);
         self.frame_counter = 0;
 
+        // for those the outputs assume that the device delivers the target rate
+        let fps = if self.video_fps > 0.0 { self.video_fps } else { conf.frames.target_fps };
+
+        // the output runs at the processed rate of the first source, as that is what it receives
+        if self.video_output.is_none() && !conf.video_output.path.is_empty() {
+            let processed_fps = fps / self.frame_skip as f64;
+            self.video_output = Some(VideoOutput::new(&conf.video_output.path, &conf.video_output.codec, processed_fps)?);
+        }
+
+        // clips are recorded at the full frame rate of the source
+        self.clip_recorder = if conf.clips.directory.is_empty() {
+            None
+        } else {
+            Some(ClipRecorder::new(&conf.clips, source, fps)?)
+        };
+
+        let result = self.process_frames(source);
+
+        // an event still going when the source ends is closed at its last frame
+        let event = self.motion_events.finish(self.frame_counter as u64, source.timestamp_ms());
+        let zone_events = self.zones.finish(self.frame_counter as u64, source.timestamp_ms());
+        self.motion_detected = false;
+        if let Some(event_log) = self.event_log.as_mut() {
+            let record = FrameRecord {
+                source: source.name().to_string(),
+                frame: self.frame_counter as u64,
+                timestamp_ms: source.timestamp_ms(),
+                scores: scores(&self.cascade),
+                shadow_fraction: None,
+                boxes: Vec::new(),
+            };
+            event_log.update(event.as_ref(), false, &record)?;
+            for zone_event in &zone_events {
+                event_log.zone(zone_event, &record)?;
+            }
+        }
+
+        if let Some(mut clip_recorder) = self.clip_recorder.take() {
+            clip_recorder.finish()?;
+            if !self.settings.output.silent && !clip_recorder.clips().is_empty() {
+                println!("Recorded {} clips from {}", clip_recorder.clips().len(), source.name());
+            }
+        }
+        if !self.settings.output.silent {
+            for tripwire in self.tripwires.lines() {
+                println!("{}: {} A>B, {} B>A", tripwire.name(), tripwire.a_to_b(), tripwire.b_to_a());
+            }
+        }
+        result
+    }
+
+    fn process_frames(&mut self, source: &mut dyn FrameSource) -> Result<()> {
+
+        let conf = &self.settings;
+        self.read_frame_retry_count = 0;
+
         // initialize by reading the first frame
         loop {
-            if self.video_frames.read_frame(&mut self.cam).is_ok() {
-                self.mse_detector = MeanSquaredError::new(&self.video_frames.mono.quarter.cur);
-                self.mog2_detector = MotionMog2::new(
-                    &self.video_frames.mono.quarter.cur,
-                    conf.mog2_history,
-                    conf.mog2_sensitivity,
-                    conf.adaptive_block_size,
-                    conf.adaptive_c,
-                );
-                break;
+            match self.video_frames.read_frame(source) {
+                Ok(true) => {
+                    self.cascade = Cascade::new(conf, &self.video_frames)?;
+                    self.motion_events = MotionEvents::new(self.cascade.threshold(), &conf.motion);
+                    self.tracker = Tracker::new(&conf.tracker);
+                    self.tripwires = Tripwires::new(
+                        &conf.tripwires,
+                        self.video_frames.size_full(),
+                        self.video_frames.size_quarter(),
+                    );
+                    self.zones = Zones::new(&conf.zones, &conf.motion, &self.video_frames)?;
+                    self.dwell = Dwell::new();
+                    self.cascade.set_mask(self.zones.detection_mask())?;
+                    break;
+                }
+                Ok(false) => {
+                    return Err(VisionError::Decode(String::from("source ended before its first frame")));
+                }
+                Err(VisionError::Decode(_)) => {}
+                Err(e) => return Err(e),
             }
             self.read_frame_retry_count += 1;
             if self.read_frame_retry_count > 9 {
-                panic!("Unable to read first frame from video file. Exiting!");
+                return Err(VisionError::Decode(String::from("unable to read the first frame")));
             }
         }
 
@@ -169,94 +335,255 @@ impl VideoProcessor {
 
             self.frame_counter += 1;
             if self.frame_counter % self.frame_skip != 0 {
-                self.cam.grab()?;
+                // frames skipped by the detectors are still decoded while clips are recorded
+                match self.clip_recorder.as_mut() {
+                    Some(clip_recorder) => {
+                        let mut frame = Mat::default();
+                        match source.read(&mut frame) {
+                            Ok(true) => clip_recorder.push(frame, source.timestamp_ms())?,
+                            Ok(false) | Err(VisionError::Decode(_)) => {}
+                            Err(e) => return Err(e),
+                        }
+                    }
+                    None => {
+                        source.skip()?;
+                    }
+                }
                 continue;
             }
 
-            if !conf.silent && conf.verbose {
-                println!(
-                    "Frame: {} of {}",
-                    self.frame_counter,
-                    self.cam.get(videoio::CAP_PROP_FRAME_COUNT)?
-                );
+            if !conf.output.silent && conf.output.verbose {
+                match source.frame_count() {
+                    Some(frame_count) => println!("Frame: {} of {}", self.frame_counter, frame_count),
+                    None => println!("Frame: {}", self.frame_counter),
+                }
             }
 
-            self.motion_detected = false;
             self.stopwatch.start();
 
-            if self.video_frames.read_frame(&mut self.cam).is_ok() {
-                self.stopwatch.lap("Read Frame");
-
-                self.mse_detector.update(&self.video_frames)?;
-                self.stopwatch.lap("MSE");
-
-                let mse_avg = self.mse_detector.get_value_avg();
-                if mse_avg >= conf.mse_threshold {
-                    self.mog2_detector.update(&self.video_frames)?;
-                    self.stopwatch.lap("MOG2");
-                    let mog2_avg = self.mog2_detector.get_area_avg();
-
-                    if mog2_avg >= conf.mog2_threshold {
-                        if !conf.silent && conf.verbose {
-                            println!(
-                                "Motion detected (Frame {}, MSE: {:.4}, MOG2:{:.0})",
-                                self.frame_counter,
-                                mse_avg,
-                                mog2_avg
-                            );
-                        }
-                    self.motion_detected = true;
+            let frame_read = match self.video_frames.read_frame(source) {
+                Ok(frame_read) => frame_read,
+                Err(VisionError::Decode(reason)) => {
+                    // a single undecodable frame is skipped rather than ending the source
+                    if !conf.output.silent {
+                        eprintln!("Skipping frame {}: {}", self.frame_counter, reason);
                     }
+                    continue;
                 }
+                Err(e) => return Err(e),
+            };
 
-            } else {
+            if !frame_read {
                 self.stopwatch.stop();
-                if !conf.silent {
-                    if conf.verbose {
-                        println!("{}", self.stopwatch.to_string_detailed());
-                    } else {
-                        println!("{}", self.stopwatch.to_string());
+                self.print_stats();
+                return Ok(());
+            }
+
+            self.stopwatch.lap("Read Frame");
+
+            self.cascade.update(&self.video_frames, &mut self.stopwatch)?;
+            let segmentation = self.cascade.segmentation();
+
+            let mut crossings = Vec::new();
+            let zone_events: Vec<ZoneEvent>;
+            if let Some(segmentation) = segmentation {
+                // tracks are only updated while something moves, a still scene leaves them be
+                self.tracker.update(&segmentation.boxes, source.timestamp_ms());
+                crossings = self.tripwires.update(&self.tracker);
+                zone_events = self.zones.update(
+                    Some(&segmentation.foreground),
+                    self.frame_counter as u64,
+                    source.timestamp_ms(),
+                )?;
+                self.stopwatch.lap("Track");
+            } else {
+                zone_events = self.zones.update(None, self.frame_counter as u64, source.timestamp_ms())?;
+            }
+            if self.cascade.is_motion() && !conf.output.silent && conf.output.verbose {
+                let scores: Vec<String> = self.cascade.stages().iter()
+                    .filter_map(|stage| stage.score().map(|score| format!("{}: {:.2}", stage.name(), score)))
+                    .collect();
+                println!("Motion detected (Frame {}, {})", self.frame_counter, scores.join(", "));
+            }
+            // dwell time runs on while a gate holds the segmenting stage back, e.g. for someone standing still
+            let loitering = self.dwell.update(&self.tracker, &self.zones, source.timestamp_ms())?;
+
+            let event = self.motion_events.update(self.frame_counter as u64, source.timestamp_ms(), self.cascade.score());
+            self.motion_detected = self.motion_events.is_active();
+            if !conf.output.silent && conf.output.verbose {
+                match event {
+                    Some(MotionEvent::MotionStarted { frame, .. }) => println!("Motion started (Frame {})", frame),
+                    Some(MotionEvent::MotionEnded { frame, duration_ms, .. }) => {
+                        println!("Motion ended (Frame {}, {:.1} s)", frame, duration_ms / 1000.0)
                     }
+                    None => {}
+                }
+                for crossing in &crossings {
+                    println!(
+                        "Track {} crossed {} ({:?}, {} A>B, {} B>A)",
+                        crossing.track_id,
+                        crossing.line,
+                        crossing.direction,
+                        crossing.a_to_b,
+                        crossing.b_to_a
+                    );
+                }
+                for zone_event in &zone_events {
+                    match zone_event.event {
+                        MotionEvent::MotionStarted { frame, .. } => {
+                            println!("Motion started in {} (Frame {})", zone_event.zone, frame)
+                        }
+                        MotionEvent::MotionEnded { frame, duration_ms, .. } => {
+                            println!("Motion ended in {} (Frame {}, {:.1} s)", zone_event.zone, frame, duration_ms / 1000.0)
+                        }
+                    }
+                }
+                for loitering in &loitering {
+                    println!(
+                        "Track {} loitering in {} ({:.1} s)",
+                        loitering.track_id,
+                        loitering.zone,
+                        loitering.duration_ms / 1000.0
+                    );
                 }
-                return Ok(());
             }
 
-            if !conf.headless {
-                if self.motion_detected {
-                    let mut motion_overlay = MotionOverlay::new(&self.mog2_detector);
-                    let overlay_frame = motion_overlay.draw(&self.video_frames.color.half.cur)?;
-                    self.stopwatch.lap("Overlay");
-                    imshow("video", &overlay_frame)?;
+            // the classifier only looks at confirmed motion, in frames that the cascade boxed
+            let boxed = if self.motion_detected { segmentation } else { None };
+            let classifications = match (self.classifier.as_mut(), boxed) {
+                (Some(classifier), Some(segmentation)) => {
+                    let classifications = classifier.classify_boxes(&segmentation.boxes, &self.video_frames)?;
+                    self.stopwatch.lap("Classify");
+                    classifications
+                }
+                _ => Vec::new(),
+            };
+
+            // so is the vision-language model, as often as its rate limit lets it
+            if let (Some(vlm), Some(segmentation)) = (self.vlm.as_mut(), boxed) {
+                vlm.submit(
+                    source.name(),
+                    self.frame_counter as u64,
+                    source.timestamp_ms(),
+                    &segmentation.boxes,
+                    &self.video_frames,
+                )?;
+            }
+            let descriptions = self.vlm.as_mut().map(|vlm| vlm.poll()).unwrap_or_default();
+            log_descriptions(descriptions, &conf.output, self.event_log.as_mut())?;
+
+            if let Some(event_log) = self.event_log.as_mut() {
+                let boxes = if let Some(segmentation) = boxed {
+                    segmentation.boxes.iter()
+                        .enumerate()
+                        .map(|(index, rect)| {
+                            let classification = classifications.get(index).cloned().flatten();
+                            BoundingBox {
+                                track_id: self.tracker.track_of(rect).map(|track| track.id()),
+                                confidence: classification.as_ref().map(|classification| classification.confidence),
+                                label: classification.map(|classification| classification.label),
+                                ..BoundingBox::from(self.video_frames.quarter_to_full(*rect))
+                            }
+                        })
+                        .collect()
                 } else {
-                    imshow("video", &self.video_frames.color.half.cur)?;
+                    Vec::new()
+                };
+                let record = FrameRecord {
+                    source: source.name().to_string(),
+                    frame: self.frame_counter as u64,
+                    timestamp_ms: source.timestamp_ms(),
+                    scores: scores(&self.cascade),
+                    shadow_fraction: segmentation.and_then(|segmentation| segmentation.shadow_fraction),
+                    boxes,
+                };
+                event_log.update(event.as_ref(), self.motion_detected, &record)?;
+                for crossing in &crossings {
+                    event_log.crossing(crossing, &record)?;
+                }
+                for zone_event in &zone_events {
+                    event_log.zone(zone_event, &record)?;
+                }
+                for loitering in &loitering {
+                    event_log.loitering(loitering, &record)?;
+                }
+                self.stopwatch.lap("Event Log");
+            }
+
+            if let Some(clip_recorder) = self.clip_recorder.as_mut() {
+                let timestamp_ms = source.timestamp_ms();
+                clip_recorder.push(self.video_frames.color.full.cur.try_clone()?, timestamp_ms)?;
+                if self.motion_detected {
+                    clip_recorder.motion(timestamp_ms)?;
+                }
+                self.stopwatch.lap("Record Clip");
+            }
+
+            let raw_frame = &self.video_frames.color.half.cur;
+            let write_overlay = self.video_output.is_some() && !conf.video_output.raw;
+            // the overlay needs the boxes of this frame, which only a segmenting stage that ran has
+            let overlay_frame = if let Some(segmentation) = boxed.filter(|_| !conf.output.headless || write_overlay) {
+                let mut motion_overlay = MotionOverlay::with_settings(&segmentation.mask, &segmentation.boxes, &conf.overlay)
+                    .with_tracker(&self.tracker)
+                    .with_classifications(&classifications);
+                if let Some(shadows) = segmentation.shadows.as_ref().filter(|_| conf.overlay.show_shadows) {
+                    motion_overlay = motion_overlay.with_shadows(shadows);
                 }
-                // imshow("mask", mog2_detector.get_diff_mask())?;
+                let overlay_frame = motion_overlay.draw(raw_frame)?;
+                self.stopwatch.lap("Overlay");
+                Some(overlay_frame)
+            } else {
+                None
+            };
+            // the tripwires and their counts are shown whether there is motion or not
+            let tripwire_frame = if !self.tripwires.is_empty() && (!conf.output.headless || write_overlay) {
+                let tripwire_frame = TripwireOverlay::with_settings(&self.tripwires, self.video_frames.size_quarter(), &conf.overlay)
+                    .draw(overlay_frame.as_ref().unwrap_or(raw_frame))?;
+                self.stopwatch.lap("Overlay");
+                Some(tripwire_frame)
+            } else {
+                None
+            };
+            let annotated_frame = tripwire_frame.as_ref().or(overlay_frame.as_ref()).unwrap_or(raw_frame);
+
+            if let Some(video_output) = self.video_output.as_mut() {
+                video_output.write(if conf.video_output.raw { raw_frame } else { annotated_frame })?;
+                self.stopwatch.lap("Write Output");
+            }
+
+            if let Some(metrics_log) = self.metrics_log.as_mut() {
+                let detectors: Vec<Option<(f64, f64)>> = self.cascade.stages().iter()
+                    .map(|stage| stage.value().zip(stage.score()))
+                    .collect();
+                metrics_log.write(&FrameMetrics {
+                    source: source.name(),
+                    frame: self.frame_counter as u64,
+                    timestamp_ms: source.timestamp_ms(),
+                    detectors: &detectors,
+                    boxes: segmentation.map_or(0, |segmentation| segmentation.boxes.len()),
+                    motion: self.motion_detected,
+                    laps: self.stopwatch.frame_laps(),
+                })?;
+            }
+
+            if !conf.output.headless {
+                imshow("video", annotated_frame)?;
+                // imshow("mask", &segmentation.mask)?;
 
                 match highgui::wait_key(1)? {
 
                     // ESC => exit
                     27 => {
                         self.stopwatch.stop();
-                        if !conf.silent {
-                            if conf.verbose {
-                                println!("{}", self.stopwatch.to_string_detailed());
-                            } else {
-                                println!("{}", self.stopwatch.to_string());
-                            }
-                        }
-                        exit(0);
+                        self.print_stats();
+                        self.stop_requested = true;
+                        return Ok(());
                     }
 
                     // q => skip video
                     113 => {
                         self.stopwatch.stop();
-                        if !conf.silent {
-                            if conf.verbose {
-                                println!("{}", self.stopwatch.to_string_detailed());
-                            } else {
-                                println!("{}", self.stopwatch.to_string());
-                            }
-                        }
+                        self.print_stats();
                         return Ok(());
                     }
 
@@ -267,4 +594,34 @@ impl VideoProcessor {
             self.stopwatch.tick();
         }
     }
-}
\ No newline at end of file
+}
+
+// the averaged score of every stage, keyed like `mse_avg`, None for stages that were skipped
+fn scores(cascade: &Cascade) -> BTreeMap<String, Option<f64>> {
+    cascade.stages().iter()
+        .map(|stage| (format!("{}_avg", stage.name().to_lowercase()), stage.score()))
+        .collect()
+}
+
+// answers from the vision-language model, for whichever frame they were asked about
+fn log_descriptions(descriptions: Vec<io::Result<Description>>, output: &OutputSettings, mut event_log: Option<&mut EventLog>) -> Result<()> {
+    for description in descriptions {
+        match description {
+            Ok(description) => {
+                if !output.silent && output.verbose {
+                    println!("Frame {} of {}: {}", description.frame, description.source, description.description);
+                }
+                if let Some(event_log) = event_log.as_mut() {
+                    event_log.description(&description)?;
+                }
+            }
+            // the model being unavailable does not hold up the motion detection
+            Err(e) => {
+                if !output.silent {
+                    eprintln!("Failed to describe motion: {}", e);
+                }
+            }
+        }
+    }
+    Ok(())
+}